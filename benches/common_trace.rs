@@ -0,0 +1,55 @@
+//! Times `Trace::common_trace_with` on synthetic pairs of traces, run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+use fc_journey_validation::{point::PointWithId, trace::Trace};
+
+const SIZES: [usize; 4] = [1_000, 5_000, 10_000, 20_000];
+const RUNS: u32 = 5;
+
+/// Winding trace sampled every second, the passenger riding a few meters aside with its own
+/// clock offset.
+fn create_trace(id: &str, count: usize, offset: f64, offset_ms: i64) -> Trace {
+    let start = Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap();
+    let points = (0..count)
+        .map(|i| {
+            let t = i as f64;
+
+            PointWithId {
+                id: format!("{id}_{i}"),
+                x: 2.3522 + t * 0.0001 + offset,
+                y: 48.8566 + (t / 200.0).sin() * 0.01 + offset,
+                trace_id: id.to_string(),
+                timestamp: start + chrono::Duration::milliseconds(i as i64 * 1_000 + offset_ms),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Trace::new(id.to_string(), points)
+}
+
+fn main() {
+    for size in SIZES {
+        let driver = create_trace("driver", size, 0.0, 0);
+        let passenger = create_trace("passenger", size, 0.00003, 300);
+
+        let mut best = Duration::MAX;
+        let mut distance = 0.0;
+
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let common_trace = driver
+                .common_trace_with(&passenger)
+                .expect("traces have common points");
+            best = best.min(start.elapsed());
+            distance = common_trace.common_distance;
+        }
+
+        println!(
+            "common_trace_with/{size:>6} points: {:>10.3} ms (distance {distance:.1} m)",
+            best.as_secs_f64() * 1_000.0
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Times `Journey::validate` end to end on synthetic pairs of traces, run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+use fc_journey_validation::{
+    config::Config,
+    input::{JourneyInput, TraceInput},
+    journey::Journey,
+    output::Output,
+};
+use serde_json::json;
+
+const SIZES: [usize; 4] = [1_000, 5_000, 10_000, 20_000];
+const RUNS: u32 = 3;
+
+/// Pseudo-random noise in `[-1, 1]`, distinct for every seed.
+fn noise(i: usize, seed: u64) -> f64 {
+    let hash = (i as u64 ^ seed)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .rotate_left(31)
+        .wrapping_mul(0xBF58_476D_1CE4_E5B9);
+
+    (hash >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+}
+
+/// Winding trace sampled every second, the passenger riding a few meters aside with its own
+/// clock offset. Each device adds its own noise of about a meter.
+fn create_trace(
+    id: &str,
+    user_id: &str,
+    count: usize,
+    offset: f64,
+    offset_ms: i64,
+    seed: u64,
+) -> TraceInput {
+    let start = Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap();
+    let points: Vec<_> = (0..count)
+        .map(|i| {
+            let t = i as f64;
+            let timestamp = start + chrono::Duration::milliseconds(i as i64 * 1_000 + offset_ms);
+
+            json!({
+                "id": format!("{id}_{i}"),
+                "createdAt": timestamp,
+                "updatedAt": timestamp,
+                "latitude": 48.8566 + (t / 200.0).sin() * 0.003 + offset + noise(i, seed) * 0.00001,
+                "longitude": 2.3522 + t * 0.00004 + offset + noise(i, !seed) * 0.00001,
+                "timestamp": timestamp,
+                "gpsTraceId": id,
+            })
+        })
+        .collect();
+
+    serde_json::from_value(json!({
+        "id": id,
+        "createdAt": start,
+        "updatedAt": start,
+        "userId": user_id,
+        "points": points,
+    }))
+    .unwrap()
+}
+
+fn main() {
+    for size in SIZES {
+        let input = JourneyInput {
+            id: None,
+            start_time: Some(Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap()),
+            end_time: Some(Utc.with_ymd_and_hms(2024, 6, 18, 18, 0, 0).unwrap()),
+            driver_id: Some("driver".to_string()),
+            passenger_id: Some("passenger".to_string()),
+            passenger_ids: None,
+            gps_trace: vec![
+                create_trace("driver_trace", "driver", size, 0.0, 0, 1),
+                create_trace("passenger_trace", "passenger", size, 0.00003, 300, 2),
+            ],
+        };
+        let journey = Journey::new(input, Config::default()).expect("valid journey");
+
+        let mut best = Duration::MAX;
+        let mut outcome = String::new();
+
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let output = journey.validate();
+            best = best.min(start.elapsed());
+            outcome = match output {
+                Output::Success(success) => format!("confidence {:.3}", success.average_confidence),
+                Output::Error(err) => err.cancel_reason,
+                _ => "vanpool output".to_string(),
+            };
+        }
+
+        println!(
+            "validate/{size:>6} points: {:>10.3} ms ({outcome})",
+            best.as_secs_f64() * 1_000.0
+        );
+    }
+}
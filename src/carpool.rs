@@ -0,0 +1,153 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    config::Config,
+    error::JourneyValidationError,
+    input::JourneyInput,
+    journey::{Journey, Networks},
+    output::{CarpoolOutput, Output, PassengerOutput, Verdict},
+    Result,
+};
+
+/// Journey of a driver carrying several passengers, each one validated against the driver.
+pub struct Carpool {
+    pub passengers: Vec<(String, Result<Journey>)>,
+}
+
+impl Carpool {
+    /// Validates each distinct passenger of `passenger_ids`, or the single `passenger_id`. When
+    /// both are set, `passenger_id` must be one of `passenger_ids`.
+    pub fn new(journey: JourneyInput, config: Config) -> Result<Self> {
+        let passenger_ids = match (&journey.passenger_ids, &journey.passenger_id) {
+            (Some(ids), Some(id)) if !ids.contains(id) => {
+                return Err(JourneyValidationError::AmbiguousPassengers)
+            }
+            (Some(ids), _) => {
+                let mut seen = HashSet::new();
+                ids.iter().filter(|id| seen.insert(*id)).cloned().collect()
+            }
+            (None, Some(id)) => vec![id.clone()],
+            (None, None) => vec![],
+        };
+
+        if passenger_ids.is_empty() {
+            return Err(JourneyValidationError::MissingPassenger);
+        }
+
+        if passenger_ids.len() > config.max_passengers.0 {
+            return Err(JourneyValidationError::TooManyPassengers);
+        }
+
+        let networks = Arc::new(Networks::load(&config)?);
+        let passengers = passenger_ids
+            .into_iter()
+            .map(|passenger_id| {
+                let passenger_journey = Journey::with_passenger(
+                    &journey,
+                    Some(&passenger_id),
+                    config.clone(),
+                    networks.clone(),
+                );
+
+                (passenger_id, passenger_journey)
+            })
+            .collect();
+
+        Ok(Self { passengers })
+    }
+
+    pub fn validate(&self) -> Output {
+        let passengers: Vec<PassengerOutput> = self
+            .passengers
+            .iter()
+            .map(|(passenger_id, journey)| PassengerOutput {
+                passenger_id: passenger_id.clone(),
+                output: match journey {
+                    Ok(journey) => journey.validate(),
+                    Err(err) => Output::from(err),
+                },
+            })
+            .collect();
+
+        let validated = passengers
+            .iter()
+            .filter(|p| matches!(p.output, Output::Success(_)))
+            .count();
+
+        let verdict = match validated {
+            0 => Verdict::Invalid,
+            n if n == passengers.len() => Verdict::Valid,
+            _ => Verdict::Partial,
+        };
+
+        Output::Carpool(CarpoolOutput {
+            verdict,
+            passengers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MaxPassengers;
+
+    fn create_input(passenger_ids: &[&str]) -> JourneyInput {
+        JourneyInput::try_from(
+            serde_json::json!({
+                "startTime": "2024-06-18T12:00:00Z",
+                "endTime": "2024-06-18T12:30:00Z",
+                "driverId": "driver",
+                "passengerIds": passenger_ids,
+                "gpsTrace": [],
+            })
+            .to_string()
+            .as_str(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_too_many_passengers() {
+        let config = Config {
+            max_passengers: MaxPassengers(2),
+            ..Default::default()
+        };
+        let carpool = Carpool::new(create_input(&["a", "b", "a"]), config.clone()).unwrap();
+
+        assert_eq!(carpool.passengers.len(), 2);
+        assert!(matches!(
+            Carpool::new(create_input(&["a", "b", "c"]), config),
+            Err(JourneyValidationError::TooManyPassengers)
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_passengers() {
+        let mut input = create_input(&["a", "b"]);
+        input.passenger_id = Some("a".to_string());
+
+        assert!(Carpool::new(input, Config::default()).is_ok());
+
+        let mut input = create_input(&["a", "b"]);
+        input.passenger_id = Some("c".to_string());
+
+        assert!(matches!(
+            Carpool::new(input, Config::default()),
+            Err(JourneyValidationError::AmbiguousPassengers)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_verdict() {
+        let carpool = Carpool::new(create_input(&["a", "b"]), Config::default()).unwrap();
+
+        match carpool.validate() {
+            Output::Carpool(output) => {
+                assert_eq!(output.verdict, Verdict::Invalid);
+                assert_eq!(output.passengers.len(), 2);
+            }
+            _ => panic!("expected a carpool output"),
+        }
+    }
+}
@@ -6,4 +6,7 @@ use clap::Parser;
 pub struct Cli {
     #[arg(short, long)]
     pub file_path: Option<PathBuf>,
+
+    #[arg(short, long)]
+    pub config_path: Option<PathBuf>,
 }
@@ -0,0 +1,480 @@
+use std::collections::{HashMap, HashSet};
+
+use geo::{
+    EuclideanDistance, HaversineBearing, HaversineDistance, HaversineLength, LineString, Point,
+    Simplify,
+};
+
+use crate::{
+    config::{CommonSegmentConfig, CommonSegmentStrategyKind},
+    error::JourneyValidationError,
+    output::PointOutput,
+    point::PointWithId,
+    spatial::{segment_index, segments_within},
+    trace::{CommonTrace, Trace},
+    Result,
+};
+
+const MAX_POINTS_DELTA_IN_METERS: f64 = 1000.0;
+const MAX_BEARING: f64 = 50.0;
+
+/// Finds the part of the journey travelled together by the driver and the passenger.
+pub trait CommonSegmentStrategy {
+    fn common_segment(&self, driver: &Trace, passenger: &Trace) -> Result<CommonTrace>;
+}
+
+pub fn strategy(config: &CommonSegmentConfig) -> Box<dyn CommonSegmentStrategy> {
+    match config.strategy {
+        CommonSegmentStrategyKind::Heuristic => Box::new(Heuristic),
+        CommonSegmentStrategyKind::Corridor => Box::new(Corridor {
+            width: config.radius,
+        }),
+        CommonSegmentStrategyKind::TimeAligned => Box::new(TimeAligned {
+            radius: config.radius,
+        }),
+        CommonSegmentStrategyKind::Lcss => Box::new(Lcss {
+            radius: config.radius,
+            max_delta_ms: config.max_delta_ms,
+        }),
+    }
+}
+
+/// Historical algorithm: keeps the points of both traces up to the last point close to the trace
+/// ending last, drops the zigzags between them and measures what remains once simplified.
+pub struct Heuristic;
+
+impl CommonSegmentStrategy for Heuristic {
+    fn common_segment(&self, driver: &Trace, passenger: &Trace) -> Result<CommonTrace> {
+        let mut all_points: Vec<&PointWithId> = driver
+            .points
+            .iter()
+            .chain(passenger.points.iter())
+            .collect();
+        all_points.sort_by_key(|p| p.timestamp);
+
+        let t0 = all_points.first().unwrap();
+        let tx = all_points.last().unwrap();
+        let trace_with_tx = if tx.trace_id == driver.id {
+            driver
+        } else {
+            passenger
+        };
+        let ls_with_tx = LineString::from(trace_with_tx);
+        let index_with_tx = segment_index([(0, &ls_with_tx)]);
+
+        let ty_data = all_points.iter().enumerate().rfind(|(_, p)| {
+            if p.trace_id == tx.trace_id {
+                return false;
+            }
+
+            let point = Point::from(**p);
+            let distance = match index_with_tx.nearest_neighbor(&point) {
+                Some(segment) => segment.geom().euclidean_distance(&point),
+                None => point.euclidean_distance(&ls_with_tx),
+            };
+
+            distance < MAX_POINTS_DELTA_IN_METERS
+        });
+
+        let (ty_idx, ty) = match ty_data {
+            Some(data) => data,
+            None => return Err(JourneyValidationError::NoCommonPoints),
+        };
+
+        let all_points = &all_points[0..=ty_idx];
+
+        let mut common_points: Vec<&PointWithId> = Vec::with_capacity(all_points.len());
+        let mut idx = 0;
+
+        while idx < all_points.len() {
+            let prev = common_points.last();
+            let mid = all_points[idx];
+            let next = all_points.get(idx + 1);
+
+            idx += 1;
+
+            if idx == 1 {
+                common_points.push(mid);
+                continue;
+            }
+
+            if mid.id == ty.id {
+                common_points.push(mid);
+                break;
+            }
+
+            if prev.is_none() || next.is_none() {
+                common_points.push(mid);
+                continue;
+            }
+
+            let prev = prev.unwrap();
+            let next = next.unwrap();
+
+            if prev.trace_id == mid.trace_id && next.trace_id == mid.trace_id {
+                common_points.push(mid);
+                continue;
+            }
+
+            let prev_point = Point::from(*prev);
+            let next_point = Point::from(*next);
+
+            let prev_next_dist = prev_point.haversine_distance(&next_point);
+
+            if prev.trace_id != mid.trace_id
+                && prev.trace_id == next.trace_id
+                && prev_next_dist < 250.0
+            {
+                common_points.push(next);
+                idx += 1;
+                continue;
+            }
+
+            let mid_point = Point::from(mid);
+            let bearing_prev = mid_point.haversine_bearing(prev_point);
+            let bearing_next = mid_point.haversine_bearing(next_point);
+            let delta = (bearing_next - bearing_prev + 360.0) % 360.0;
+            let angle = if delta <= 180.0 { delta } else { 360.0 - delta };
+
+            if angle >= MAX_BEARING {
+                common_points.push(mid);
+                continue;
+            }
+        }
+
+        common_points.sort_by_key(|p| p.timestamp);
+        let mut filtered_points = PointsById::with_capacity(common_points.len());
+
+        let window_size = 5;
+        let max = window_size - 1;
+
+        if common_points.len() > window_size {
+            for window in common_points.windows(window_size) {
+                if window[0].id == t0.id {
+                    filtered_points.push(window[0]);
+                    continue;
+                }
+
+                if window[max].id == ty.id {
+                    filtered_points.push(window[max]);
+                }
+
+                if filtered_points.contains(window[0]) {
+                    continue;
+                }
+
+                let start_point = Point::from(window[0]);
+                let end_point = Point::from(window[max]);
+                let size = start_point.haversine_distance(&end_point);
+
+                if size > 100.0 {
+                    filtered_points.push(window[0]);
+                    continue;
+                }
+
+                let trace_ids_count = window
+                    .iter()
+                    .map(|p| p.trace_id.clone())
+                    .collect::<Vec<String>>()
+                    .iter()
+                    .fold(HashMap::new(), |mut acc, id| {
+                        *acc.entry(id.clone()).or_insert(0) += 1;
+                        acc
+                    });
+
+                if trace_ids_count.values().any(|&v| v >= max - 1) {
+                    for &point in window {
+                        if trace_ids_count[&point.trace_id] >= max - 1 {
+                            filtered_points.push(point);
+                        }
+                    }
+                }
+
+                filtered_points.push(window[0]);
+            }
+
+            common_points = filtered_points.points;
+            common_points.sort_by_key(|p| p.timestamp);
+            filtered_points = PointsById::with_capacity(common_points.len());
+
+            let window_size = 2;
+            let max = window_size - 1;
+
+            for window in common_points.windows(window_size) {
+                if filtered_points.contains(window[0]) {
+                    continue;
+                }
+
+                if window[0].id == t0.id {
+                    filtered_points.push(window[0]);
+                }
+
+                if window[max].id == ty.id {
+                    filtered_points.push(window[max]);
+                }
+
+                let prev = filtered_points.points.last();
+
+                if prev.is_none() {
+                    continue;
+                }
+
+                let prev = prev.unwrap();
+
+                let prev_point = Point::from(*prev);
+                let mid_point = Point::from(window[0]);
+                let next_point = Point::from(window[max]);
+                let bearing_prev = mid_point.haversine_bearing(prev_point);
+                let bearing_next = mid_point.haversine_bearing(next_point);
+                let delta = (bearing_next - bearing_prev + 360.0) % 360.0;
+                let angle = if delta <= 180.0 { delta } else { 360.0 - delta };
+
+                if angle == 0.0 || angle >= MAX_BEARING {
+                    filtered_points.push(window[0]);
+                }
+            }
+        } else {
+            filtered_points.points.clone_from(&common_points);
+        }
+
+        let mut filtered_points = filtered_points.points;
+        filtered_points.sort_by_key(|p| p.timestamp);
+        let common_linestring = LineString::from(filtered_points).simplify(&0.00001);
+        let common_distance = common_linestring.haversine_length();
+
+        // visualize([
+        //     (common_linestring, FeatureProperties::new().color("#00ffff")),
+        //     (
+        //         LineString::from(driver),
+        //         FeatureProperties::new().color("#ff0000"),
+        //     ),
+        //     (
+        //         LineString::from(passenger),
+        //         FeatureProperties::new().color("#00ff00"),
+        //     ),
+        // ]);
+
+        Ok(CommonTrace {
+            common_distance,
+            common_start_point: PointOutput::from(*t0),
+            common_end_point: PointOutput::from(*ty),
+        })
+    }
+}
+
+/// Points kept in insertion order, with a constant time lookup by id.
+struct PointsById<'a> {
+    points: Vec<&'a PointWithId>,
+    ids: HashSet<&'a str>,
+}
+
+impl<'a> PointsById<'a> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            points: Vec::with_capacity(capacity),
+            ids: HashSet::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, point: &'a PointWithId) {
+        self.ids.insert(point.id.as_str());
+        self.points.push(point);
+    }
+
+    fn contains(&self, point: &PointWithId) -> bool {
+        self.ids.contains(point.id.as_str())
+    }
+}
+
+/// Passenger points within `width` meters of the driver trajectory, whatever the time.
+pub struct Corridor {
+    pub width: f64,
+}
+
+impl CommonSegmentStrategy for Corridor {
+    fn common_segment(&self, driver: &Trace, passenger: &Trace) -> Result<CommonTrace> {
+        let linestring = LineString::from(driver);
+        let index = segment_index([(0, &linestring)]);
+
+        let inside: Vec<bool> = passenger
+            .points
+            .iter()
+            .map(|p| {
+                segments_within(&index, Point::from(p), self.width)
+                    .next()
+                    .is_some()
+            })
+            .collect();
+
+        shared_segment(&passenger.points, &inside)
+    }
+}
+
+/// Passenger points within `radius` meters of the driver position at the same time.
+pub struct TimeAligned {
+    pub radius: f64,
+}
+
+impl CommonSegmentStrategy for TimeAligned {
+    fn common_segment(&self, driver: &Trace, passenger: &Trace) -> Result<CommonTrace> {
+        shared_segment(
+            &passenger.points,
+            &passenger.together_with(driver, self.radius),
+        )
+    }
+}
+
+/// Longest common subsequence of both traces, two points matching when they are within
+/// `radius` meters and `max_delta_ms` of each other.
+pub struct Lcss {
+    pub radius: f64,
+    pub max_delta_ms: i64,
+}
+
+/// Common subsequence of both traces, indices and distance being along the passenger trace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Subsequence {
+    pub length: usize,
+    pub first: Option<usize>,
+    pub last: Option<usize>,
+    pub distance: f64,
+}
+
+impl Lcss {
+    fn matches(&self, a: &PointWithId, b: &PointWithId) -> bool {
+        a.get_ms_delta_with(b) <= self.max_delta_ms
+            && Point::from(a).haversine_distance(&Point::from(b)) <= self.radius
+    }
+
+    pub fn subsequence(&self, driver: &Trace, passenger: &Trace) -> Subsequence {
+        let points = &passenger.points;
+        // Two rows of the table, over the previous and the current driver points: columns over the
+        // passenger points, the first one staying empty
+        let mut previous = vec![Subsequence::default(); points.len() + 1];
+        let mut current = vec![Subsequence::default(); points.len() + 1];
+
+        for driver_point in &driver.points {
+            for (j, point) in points.iter().enumerate() {
+                current[j + 1] = if self.matches(driver_point, point) {
+                    let diagonal = previous[j];
+
+                    Subsequence {
+                        length: diagonal.length + 1,
+                        first: diagonal.first.or(Some(j)),
+                        last: Some(j),
+                        distance: diagonal.distance
+                            + diagonal.last.map_or(0.0, |last| {
+                                Point::from(&points[last]).haversine_distance(&Point::from(point))
+                            }),
+                    }
+                } else if previous[j + 1].length >= current[j].length {
+                    previous[j + 1]
+                } else {
+                    current[j]
+                };
+            }
+
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        previous[points.len()]
+    }
+}
+
+impl CommonSegmentStrategy for Lcss {
+    fn common_segment(&self, driver: &Trace, passenger: &Trace) -> Result<CommonTrace> {
+        let points = &passenger.points;
+
+        match self.subsequence(driver, passenger) {
+            Subsequence {
+                first: Some(first),
+                last: Some(last),
+                distance,
+                ..
+            } => Ok(CommonTrace {
+                common_distance: distance,
+                common_start_point: PointOutput::from(&points[first]),
+                common_end_point: PointOutput::from(&points[last]),
+            }),
+            _ => Err(JourneyValidationError::NoCommonPoints),
+        }
+    }
+}
+
+/// Segment between the first and last shared points, its distance only counting the moves
+/// between two shared points.
+fn shared_segment(points: &[PointWithId], shared: &[bool]) -> Result<CommonTrace> {
+    let first = shared.iter().position(|s| *s);
+    let last = shared.iter().rposition(|s| *s);
+
+    let (Some(first), Some(last)) = (first, last) else {
+        return Err(JourneyValidationError::NoCommonPoints);
+    };
+
+    let common_distance = (first + 1..=last)
+        .filter(|idx| shared[idx - 1] && shared[*idx])
+        .fold(0.0, |acc, idx| {
+            acc + Point::from(&points[idx - 1]).haversine_distance(&Point::from(&points[idx]))
+        });
+
+    Ok(CommonTrace {
+        common_distance,
+        common_start_point: PointOutput::from(&points[first]),
+        common_end_point: PointOutput::from(&points[last]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::eastward_trace;
+    use chrono::Duration;
+
+    #[test]
+    fn test_strategies() {
+        let driver = eastward_trace("driver", 0, 20, 0.0);
+        let passenger = eastward_trace("passenger", 5, 15, 0.0001);
+
+        for kind in [
+            CommonSegmentStrategyKind::Corridor,
+            CommonSegmentStrategyKind::TimeAligned,
+            CommonSegmentStrategyKind::Lcss,
+        ] {
+            let common = strategy(&CommonSegmentConfig {
+                strategy: kind,
+                ..Default::default()
+            })
+            .common_segment(&driver, &passenger)
+            .unwrap();
+
+            assert_eq!(common.common_start_point.id, "passenger_30", "{kind:?}");
+            assert_eq!(common.common_end_point.id, "passenger_90", "{kind:?}");
+            assert!(
+                (common.common_distance - 8_780.0).abs() < 10.0,
+                "{kind:?} {}",
+                common.common_distance
+            );
+        }
+    }
+
+    #[test]
+    fn test_time_shifted_traces() {
+        let driver = eastward_trace("driver", 0, 20, 0.0);
+        let mut passenger = eastward_trace("passenger", 5, 15, 0.0);
+        passenger
+            .points
+            .iter_mut()
+            .for_each(|p| p.timestamp += Duration::hours(1));
+
+        let config = CommonSegmentConfig::default();
+        let corridor = Corridor {
+            width: config.radius,
+        };
+        let time_aligned = TimeAligned {
+            radius: config.radius,
+        };
+
+        assert!(corridor.common_segment(&driver, &passenger).is_ok());
+        assert!(time_aligned.common_segment(&driver, &passenger).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+use std::path::PathBuf;
+
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::error::JourneyValidationError;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Estimate the clock skew between both traces, a search over every offset, disabled when
+    /// missing.
+    pub clock_skew: Option<ClockSkewConfig>,
+    /// Resample both traces before comparing them, disabled when missing.
+    pub resample: Option<ResampleStep>,
+    /// Match both traces against a local road network, disabled when missing.
+    pub map_matching: Option<MapMatchingConfig>,
+    pub stops: StopsConfig,
+    pub transport_mode: TransportModeConfig,
+    /// Reject journeys following a rail shape of a local GTFS feed, disabled when missing.
+    pub railway: Option<RailwayConfig>,
+    pub spoofing: SpoofingConfig,
+    pub consistency: ConsistencyConfig,
+    pub latency: LatencyConfig,
+    pub duplicate: DuplicateConfig,
+    /// Reject traces already submitted in another journey, disabled when missing.
+    pub fingerprint: Option<FingerprintConfig>,
+    /// Check the journey against the other journeys of its users, disabled when missing.
+    pub history: Option<HistoryConfig>,
+    pub role_swap: RoleSwapConfig,
+    pub pickup: PickupConfig,
+    /// Passengers a vanpool may carry, more are rejected.
+    pub max_passengers: MaxPassengers,
+    /// Traces a single user may submit for one journey, merged before the validation.
+    pub max_fragments: MaxFragments,
+    pub simplify: SimplifyConfig,
+    pub common_segment: CommonSegmentConfig,
+    pub corridor: CorridorConfig,
+    /// Compute alternative similarity metrics, quadratic in the number of points, disabled when
+    /// missing.
+    pub similarity: Option<SimilarityConfig>,
+    pub confidence_profile: ConfidenceProfileConfig,
+}
+
+/// Passengers a vanpool may carry.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct MaxPassengers(pub usize);
+
+impl Default for MaxPassengers {
+    fn default() -> Self {
+        Self(4)
+    }
+}
+
+/// Traces a single user may submit for one journey.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct MaxFragments(pub usize);
+
+impl Default for MaxFragments {
+    fn default() -> Self {
+        Self(5)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ResampleStep {
+    TimeMs(i64),
+    Meters(f64),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClockSkewConfig {
+    /// Shift the passenger timestamps by the estimated offset before the timestamp-based rules.
+    pub apply: bool,
+    pub max_search_ms: i64,
+    pub step_ms: i64,
+    /// Skews above this value are flagged as implausible, and rejected when `apply` is set.
+    pub max_plausible_ms: i64,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            apply: false,
+            max_search_ms: 600_000,
+            step_ms: 1_000,
+            max_plausible_ms: 300_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MapMatchingConfig {
+    /// GeoJSON export of the road ways of an OSM PBF extract.
+    pub road_network_path: PathBuf,
+    pub search_radius: f64,
+    pub gps_sigma: f64,
+    pub beta: f64,
+}
+
+impl Default for MapMatchingConfig {
+    fn default() -> Self {
+        Self {
+            road_network_path: PathBuf::new(),
+            search_radius: 50.0,
+            gps_sigma: 10.0,
+            beta: 50.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StopsConfig {
+    pub radius: f64,
+    pub min_duration_ms: i64,
+}
+
+impl Default for StopsConfig {
+    fn default() -> Self {
+        Self {
+            radius: 50.0,
+            min_duration_ms: 120_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransportModeConfig {
+    /// Minimum share of the common segment driven by car, the rule is disabled when missing.
+    pub min_car_ratio: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RailwayConfig {
+    /// Directory of the GTFS feed, holding at least `shapes.txt`.
+    pub gtfs_path: PathBuf,
+    pub max_distance: f64,
+    pub min_ratio: f64,
+    /// Average speed in meters per second only a train reaches, above the motorway limit.
+    pub min_speed: f64,
+    /// Lower average speed accepted when the journey starts and ends at a station, above the
+    /// limit of the roads following most rail lines.
+    pub station_min_speed: f64,
+    pub station_radius: f64,
+}
+
+impl Default for RailwayConfig {
+    fn default() -> Self {
+        Self {
+            gtfs_path: PathBuf::new(),
+            max_distance: 30.0,
+            min_ratio: 0.8,
+            min_speed: 36.0,
+            station_min_speed: 25.0,
+            station_radius: 300.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpoofingConfig {
+    /// Score from which a trace is flagged for review.
+    pub review_score: Option<f64>,
+    /// Score from which the journey is rejected, disabled when missing.
+    pub reject_score: Option<f64>,
+}
+
+impl Default for SpoofingConfig {
+    fn default() -> Self {
+        Self {
+            review_score: Some(0.5),
+            reject_score: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConsistencyConfig {
+    /// Minimum share of reported speeds matching the computed ones, disabled when missing.
+    pub min_speed_ratio: Option<f64>,
+    /// Minimum share of reported headings matching the computed bearings, disabled when missing.
+    pub min_heading_ratio: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LatencyConfig {
+    /// Points created on the server later than this after their fix are considered backfilled.
+    pub backfill_threshold_ms: i64,
+    /// Maximum share of backfilled points of a trace, disabled when missing.
+    pub max_backfill_ratio: Option<f64>,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            backfill_threshold_ms: 3_600_000,
+            max_backfill_ratio: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DuplicateConfig {
+    /// Share of copied coordinates from which the passenger trace is rejected.
+    pub threshold: f64,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        Self { threshold: 0.5 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FingerprintConfig {
+    /// JSON lines file shared by every validation run.
+    pub store_path: PathBuf,
+    /// Geohash length of the cells, 7 is about 150 meters.
+    pub precision: usize,
+    /// Minimum share of geohash shingles in common, regular commutes reach it too.
+    pub min_similarity: f64,
+    /// Maximum distance in meters between two timeline samples of a replay.
+    pub replay_radius: f64,
+    /// Minimum share of timeline samples within `replay_radius`, which sets a replay apart from
+    /// the same commute driven another day.
+    pub min_replay_ratio: f64,
+}
+
+impl Default for FingerprintConfig {
+    fn default() -> Self {
+        Self {
+            store_path: PathBuf::new(),
+            precision: 7,
+            min_similarity: 0.9,
+            replay_radius: 50.0,
+            min_replay_ratio: 0.9,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryConfig {
+    /// JSON lines file shared by every validation run.
+    pub store_path: PathBuf,
+    /// Fastest plausible speed between two journeys of the same user, in meters per second.
+    pub max_speed: f64,
+    /// Maximum number of journeys per user and per day, disabled when missing.
+    pub max_daily_journeys: Option<usize>,
+    /// Time zone the days of `max_daily_journeys` start in.
+    pub timezone: Tz,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            store_path: PathBuf::new(),
+            max_speed: 50.0,
+            max_daily_journeys: None,
+            timezone: Tz::Europe__Paris,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RoleSwapConfig {
+    /// Maximum distance between the passenger and the start of the driver trace at pickup.
+    pub radius: f64,
+    /// Distance travelled alone before pickup and after drop-off to look like a driver.
+    pub min_distance: f64,
+    /// Reject suspected role swaps instead of only flagging them for review.
+    pub reject: bool,
+}
+
+impl Default for RoleSwapConfig {
+    fn default() -> Self {
+        Self {
+            radius: 200.0,
+            min_distance: 500.0,
+            reject: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PickupConfig {
+    /// Maximum distance between both users while travelling together.
+    pub radius: f64,
+    /// Consecutive passenger points next to the driver to consider the passenger on board.
+    pub min_points: usize,
+}
+
+impl Default for PickupConfig {
+    fn default() -> Self {
+        Self {
+            radius: 100.0,
+            min_points: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SimplifyAlgorithm {
+    #[default]
+    RamerDouglasPeucker,
+    VisvalingamWhyatt,
+    /// Visvalingam–Whyatt without introducing self-intersections, plain Visvalingam–Whyatt when
+    /// the trace goes back over one of its fixes.
+    TopologyPreserving,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimplifyConfig {
+    pub algorithm: SimplifyAlgorithm,
+    /// Tolerance in meters, Visvalingam–Whyatt drops the triangles smaller than its square.
+    /// Defaults to the historical epsilon of 0.00001 degree.
+    pub tolerance: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CommonSegmentStrategyKind {
+    #[default]
+    Heuristic,
+    Corridor,
+    TimeAligned,
+    Lcss,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommonSegmentConfig {
+    pub strategy: CommonSegmentStrategyKind,
+    /// Maximum distance between both traces, the corridor half-width.
+    pub radius: f64,
+    /// Maximum time between two matching points of the LCSS strategy.
+    pub max_delta_ms: i64,
+}
+
+impl Default for CommonSegmentConfig {
+    fn default() -> Self {
+        Self {
+            strategy: CommonSegmentStrategyKind::Heuristic,
+            radius: 100.0,
+            max_delta_ms: 90_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CorridorConfig {
+    /// Half-width of the corridor around each trace, in meters.
+    pub width: f64,
+    /// Widen the corridor by the accuracy of the points on both sides.
+    pub use_accuracy: bool,
+    /// Accuracies are capped so a single bad fix does not swallow the whole map.
+    pub max_accuracy: f64,
+    /// Minimum share of the passenger trace inside the driver corridor, disabled when missing.
+    pub min_passenger_ratio: Option<f64>,
+}
+
+impl Default for CorridorConfig {
+    fn default() -> Self {
+        Self {
+            width: 100.0,
+            use_accuracy: false,
+            max_accuracy: 50.0,
+            min_passenger_ratio: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimilarityConfig {
+    pub lcss_radius: f64,
+    pub lcss_max_delta_ms: i64,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        Self {
+            lcss_radius: 100.0,
+            lcss_max_delta_ms: 90_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConfidenceProfileConfig {
+    /// Length of the route segments, in meters.
+    pub segment_length: f64,
+    /// Confidence a segment must reach to count as travelled together, the rule is disabled
+    /// when missing.
+    pub min_confidence: Option<f64>,
+    /// Minimum share of the route distance reaching `min_confidence`.
+    pub min_share: f64,
+    /// Fréchet distance in meters between both traces from which a segment has no confidence.
+    pub max_distance: f64,
+}
+
+impl Default for ConfidenceProfileConfig {
+    fn default() -> Self {
+        Self {
+            segment_length: 500.0,
+            min_confidence: None,
+            min_share: 0.8,
+            max_distance: 300.0,
+        }
+    }
+}
+
+impl TryFrom<PathBuf> for Config {
+    type Error = JourneyValidationError;
+
+    fn try_from(value: PathBuf) -> std::prelude::v1::Result<Self, Self::Error> {
+        let json_string = std::fs::read_to_string(value)?;
+        let config: Config = serde_json::from_str(&json_string)?;
+
+        Ok(config)
+    }
+}
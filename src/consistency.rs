@@ -0,0 +1,143 @@
+use geo::{HaversineBearing, HaversineDistance, Point};
+
+use crate::{output::ConsistencyOutput, trace::Trace};
+
+/// Shorter moves are dominated by the GPS noise.
+const MIN_SEGMENT_DISTANCE: f64 = 5.0;
+/// Below this speed the heading reported by the device is meaningless.
+const MIN_HEADING_SPEED: f64 = 2.0;
+const SPEED_ABSOLUTE_TOLERANCE: f64 = 3.0;
+const SPEED_RELATIVE_TOLERANCE: f64 = 0.3;
+const HEADING_TOLERANCE: f64 = 45.0;
+
+/// Share of the fixes whose reported speed and heading agree with the ones derived from the
+/// previous fix, `None` when the device did not report them.
+#[derive(Debug, Clone, Default)]
+pub struct Consistency {
+    pub speed_ratio: Option<f64>,
+    pub heading_ratio: Option<f64>,
+    pub speed_samples: usize,
+    pub heading_samples: usize,
+}
+
+fn angle_between(a: f64, b: f64) -> f64 {
+    let delta = (a - b).rem_euclid(360.0);
+
+    if delta <= 180.0 {
+        delta
+    } else {
+        360.0 - delta
+    }
+}
+
+fn ratio(consistent: usize, samples: usize) -> Option<f64> {
+    (samples > 0).then(|| consistent as f64 / samples as f64)
+}
+
+impl<T> Trace<T> {
+    pub fn consistency(&self) -> Consistency {
+        let (mut speed_samples, mut speed_consistent) = (0, 0);
+        let (mut heading_samples, mut heading_consistent) = (0, 0);
+
+        for window in self.points.windows(2) {
+            let (prev, next) = (&window[0], &window[1]);
+
+            if !next.is_recorded() {
+                continue;
+            }
+
+            let (prev_point, next_point) = (Point::from(prev), Point::from(next));
+            let distance = prev_point.haversine_distance(&next_point);
+            let seconds = next.get_ms_delta_with(prev) as f64 / 1000.0;
+
+            if seconds == 0.0 || distance < MIN_SEGMENT_DISTANCE {
+                continue;
+            }
+
+            let speed = distance / seconds;
+
+            if let Some(reported) = next.speed.filter(|s| *s >= 0.0) {
+                speed_samples += 1;
+
+                let tolerance = SPEED_ABSOLUTE_TOLERANCE.max(speed * SPEED_RELATIVE_TOLERANCE);
+
+                if (reported - speed).abs() <= tolerance {
+                    speed_consistent += 1;
+                }
+            }
+
+            if speed < MIN_HEADING_SPEED {
+                continue;
+            }
+
+            if let Some(reported) = next.heading.filter(|h| *h >= 0.0) {
+                heading_samples += 1;
+
+                let bearing = prev_point.haversine_bearing(next_point);
+
+                if angle_between(reported as f64, bearing) <= HEADING_TOLERANCE {
+                    heading_consistent += 1;
+                }
+            }
+        }
+
+        Consistency {
+            speed_ratio: ratio(speed_consistent, speed_samples),
+            heading_ratio: ratio(heading_consistent, heading_samples),
+            speed_samples,
+            heading_samples,
+        }
+    }
+}
+
+impl From<&Consistency> for ConsistencyOutput {
+    fn from(value: &Consistency) -> Self {
+        Self {
+            speed_ratio: value.speed_ratio,
+            heading_ratio: value.heading_ratio,
+            speed_samples: value.speed_samples,
+            heading_samples: value.heading_samples,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    fn create_trace(speed: f64, heading: f32) -> Trace {
+        // Heading north at ~11 meters per second
+        TraceBuilder::new("trace_1")
+            .fixes(0..10, |i| {
+                (
+                    2.3522,
+                    48.8566 + i as f64 * 0.001,
+                    Duration::seconds(i * 10),
+                )
+            })
+            .with(|_, p| {
+                p.speed = Some(speed);
+                p.heading = Some(heading);
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_consistent_trace() {
+        let consistency = create_trace(11.0, 2.0).consistency();
+
+        assert_eq!(consistency.speed_samples, 9);
+        assert_eq!(consistency.speed_ratio, Some(1.0));
+        assert_eq!(consistency.heading_ratio, Some(1.0));
+    }
+
+    #[test]
+    fn test_fabricated_trace() {
+        let consistency = create_trace(30.0, 180.0).consistency();
+
+        assert_eq!(consistency.speed_ratio, Some(0.0));
+        assert_eq!(consistency.heading_ratio, Some(0.0));
+    }
+}
@@ -0,0 +1,151 @@
+use geo::{HaversineDistance, Line, Point};
+use rstar::{primitives::GeomWithData, RTree};
+
+use crate::{
+    config::CorridorConfig,
+    output::CorridorOutput,
+    spatial::{segments_within, SegmentIndex},
+    trace::Trace,
+};
+
+/// Part of a trace inside the corridor around another trace.
+#[derive(Debug, Clone, Default)]
+pub struct CorridorOverlap {
+    pub inside_distance: f64,
+    pub distance: f64,
+    pub ratio: f64,
+}
+
+impl<T> Trace<T> {
+    fn accuracy_at(&self, idx: usize, config: &CorridorConfig) -> f64 {
+        if !config.use_accuracy {
+            return 0.0;
+        }
+
+        self.points[idx]
+            .accuracy
+            .unwrap_or(0.0)
+            .clamp(0.0, config.max_accuracy)
+    }
+
+    /// Segments tagged with the index of their first point.
+    fn point_segment_index(&self) -> SegmentIndex {
+        RTree::bulk_load(
+            self.points
+                .windows(2)
+                .enumerate()
+                .map(|(idx, w)| {
+                    GeomWithData::new(Line::new(Point::from(&w[0]), Point::from(&w[1])), idx)
+                })
+                .collect(),
+        )
+    }
+
+    /// Length of this trace inside the corridor of `config.width` meters around `other`, both
+    /// widened by the accuracy of their points when `config.use_accuracy` is set.
+    pub fn corridor_overlap<U>(
+        &self,
+        other: &Trace<U>,
+        config: &CorridorConfig,
+    ) -> CorridorOverlap {
+        let index = other.point_segment_index();
+        let max_other_accuracy = (0..other.points.len())
+            .map(|idx| other.accuracy_at(idx, config))
+            .fold(0.0, f64::max);
+
+        let inside: Vec<bool> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| {
+                let width = config.width + self.accuracy_at(idx, config);
+
+                segments_within(&index, Point::from(p), width + max_other_accuracy).any(
+                    |(segment, _, distance)| {
+                        let segment_accuracy = other
+                            .accuracy_at(segment.data, config)
+                            .max(other.accuracy_at(segment.data + 1, config));
+
+                        distance <= width + segment_accuracy
+                    },
+                )
+            })
+            .collect();
+
+        let (inside_distance, distance) = self.points.windows(2).zip(inside.windows(2)).fold(
+            (0.0, 0.0),
+            |(inside_distance, distance), (points, inside)| {
+                let length = Point::from(&points[0]).haversine_distance(&Point::from(&points[1]));
+
+                if inside[0] && inside[1] {
+                    (inside_distance + length, distance + length)
+                } else {
+                    (inside_distance, distance + length)
+                }
+            },
+        );
+
+        CorridorOverlap {
+            inside_distance,
+            distance,
+            ratio: if distance > 0.0 {
+                inside_distance / distance
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl From<&CorridorOverlap> for CorridorOutput {
+    fn from(value: &CorridorOverlap) -> Self {
+        Self {
+            inside_distance: value.inside_distance,
+            distance: value.distance,
+            ratio: value.ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    /// Trace heading east, `dy` degrees north of the driver, with the given accuracy.
+    fn create_trace(id: &str, count: i64, dy: f64, accuracy: f64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..count, |i| {
+                (
+                    2.3522 + i as f64 * 0.002,
+                    48.8566 + if i < count / 2 { 0.0 } else { dy },
+                    Duration::seconds(i * 10),
+                )
+            })
+            .with(|_, p| p.accuracy = Some(accuracy))
+            .build()
+    }
+
+    #[test]
+    fn test_corridor_overlap() {
+        let driver = create_trace("driver", 41, 0.0, 30.0);
+        // The second half of the passenger trace is ~140 meters north of the driver
+        let passenger = create_trace("passenger", 21, 0.00125, 30.0);
+        let config = CorridorConfig::default();
+
+        let overlap = passenger.corridor_overlap(&driver, &config);
+        let reverse = driver.corridor_overlap(&passenger, &config);
+        let widened = passenger.corridor_overlap(
+            &driver,
+            &CorridorConfig {
+                use_accuracy: true,
+                ..config
+            },
+        );
+
+        assert!((overlap.ratio - 0.45).abs() < 0.01);
+        assert!((reverse.ratio - 0.225).abs() < 0.01);
+        assert_eq!(widened.ratio, 1.0);
+    }
+}
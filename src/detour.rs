@@ -0,0 +1,101 @@
+use geo::{HaversineDistance, Point};
+
+use crate::{output::DetourOutput, pickup::PickupDropOff, trace::Trace};
+
+/// Distances driven by the driver, and the part of it caused by carrying the passenger.
+#[derive(Debug, Clone, Default)]
+pub struct Detour {
+    pub total_distance: f64,
+    pub shared_distance: f64,
+    /// Estimated distance of the driver going straight from its start to its end.
+    pub direct_distance: f64,
+    pub detour_distance: f64,
+    pub detour_ratio: f64,
+}
+
+impl<T> Trace<T> {
+    /// The direct distance is the driven distance scaled by the ratio between the straight line
+    /// from start to end and the straight lines through the pickup and drop-off points, which
+    /// keeps the road circuity of the actual journey.
+    pub fn detour(&self, pickup_drop_off: &PickupDropOff) -> Detour {
+        let (start, end) = self.get_edges();
+        let (pickup, drop_off) = (pickup_drop_off.pickup, pickup_drop_off.drop_off);
+
+        let total_distance = self.haversine_length();
+        let shared_distance = self.distance_between(pickup.timestamp, drop_off.timestamp);
+
+        let [start, pickup, drop_off, end] = [start, pickup, drop_off, end].map(Point::from);
+        let straight = start.haversine_distance(&end);
+        let through_passenger = start.haversine_distance(&pickup)
+            + pickup.haversine_distance(&drop_off)
+            + drop_off.haversine_distance(&end);
+
+        let direct_distance = if through_passenger > 0.0 {
+            total_distance * (straight / through_passenger).min(1.0)
+        } else {
+            total_distance
+        };
+        let detour_distance = total_distance - direct_distance;
+
+        Detour {
+            total_distance,
+            shared_distance,
+            direct_distance,
+            detour_distance,
+            detour_ratio: if direct_distance > 0.0 {
+                detour_distance / direct_distance
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl From<&Detour> for DetourOutput {
+    fn from(value: &Detour) -> Self {
+        Self {
+            total_distance: value.total_distance,
+            shared_distance: value.shared_distance,
+            direct_distance: value.direct_distance,
+            detour_distance: value.detour_distance,
+            detour_ratio: value.detour_ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    #[test]
+    fn test_detour() {
+        // The driver goes 2 km north to pick up the passenger, then comes back to its way east
+        let coords = [
+            (0.0, 0.0),
+            (0.0, 0.009),
+            (0.0, 0.018),
+            (0.0135, 0.018),
+            (0.027, 0.018),
+            (0.027, 0.009),
+            (0.027, 0.0),
+        ];
+        let driver = TraceBuilder::new("driver")
+            .fixes(0..coords.len() as i64, |i| {
+                let (dx, dy) = coords[i as usize];
+                (2.3522 + dx, 48.8566 + dy, Duration::minutes(i))
+            })
+            .build();
+
+        let detour = driver.detour(&PickupDropOff {
+            pickup: &driver.points[2],
+            drop_off: &driver.points[4],
+        });
+
+        assert!((detour.total_distance - 5_978.0).abs() < 1.0);
+        assert!((detour.shared_distance - 1_975.0).abs() < 1.0);
+        assert!((detour.direct_distance - detour.shared_distance).abs() < 1.0);
+        assert!((detour.detour_ratio - 2.0).abs() < 0.05);
+    }
+}
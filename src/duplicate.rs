@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{output::DuplicateOutput, point::PointWithId, trace::Trace};
+
+/// Largest index shift tried when aligning both traces.
+const MAX_ALIGNMENT_SHIFT: i64 = 10;
+/// Offsets are compared at ~0.1 mm, far below the noise between two real devices.
+const OFFSET_PRECISION: f64 = 1e9;
+
+/// Shares of the other trace that look copied from this one.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateAnalysis {
+    pub identical_coordinates: f64,
+    pub shifted_coordinates: f64,
+    pub identical_timestamps: f64,
+    pub shifted_timestamps: f64,
+}
+
+impl DuplicateAnalysis {
+    /// Timestamps alone are not enough, devices sampling on the GPS second share them.
+    pub fn is_duplicate(&self, threshold: f64) -> bool {
+        self.identical_coordinates >= threshold || self.shifted_coordinates >= threshold
+    }
+}
+
+/// Drops the fixes repeating the previous coordinates, a device holding its last fix through a
+/// stop would otherwise add pairs with a constant offset.
+fn moving<'a>(points: &[&'a PointWithId]) -> Vec<&'a PointWithId> {
+    let moved = points
+        .windows(2)
+        .filter(|w| (w[0].x, w[0].y) != (w[1].x, w[1].y))
+        .map(|w| w[1]);
+
+    points.first().copied().into_iter().chain(moved).collect()
+}
+
+/// Best share over all index alignments of the pairs sharing the most frequent delta.
+fn best_aligned_mode<K, F>(a: &[&PointWithId], b: &[&PointWithId], delta: F) -> f64
+where
+    K: std::hash::Hash + Eq,
+    F: Fn(&PointWithId, &PointWithId) -> K,
+{
+    let min_pairs = a.len().min(b.len()) / 2;
+
+    (-MAX_ALIGNMENT_SHIFT..=MAX_ALIGNMENT_SHIFT)
+        .filter_map(|shift| {
+            let pairs: Vec<(&PointWithId, &PointWithId)> = a
+                .iter()
+                .enumerate()
+                .filter_map(|(i, p)| {
+                    let j = usize::try_from(i as i64 + shift).ok()?;
+                    b.get(j).map(|q| (*p, *q))
+                })
+                .collect();
+
+            if pairs.is_empty() || pairs.len() < min_pairs {
+                return None;
+            }
+
+            let counts = pairs.iter().fold(HashMap::new(), |mut acc, (p, q)| {
+                *acc.entry(delta(p, q)).or_insert(0) += 1;
+                acc
+            });
+
+            counts
+                .values()
+                .max()
+                .map(|max| *max as f64 / pairs.len() as f64)
+        })
+        .fold(0.0, f64::max)
+}
+
+impl<T> Trace<T> {
+    pub fn duplicate_analysis<U>(&self, other: &Trace<U>) -> DuplicateAnalysis {
+        let points: Vec<&PointWithId> = self.points.iter().filter(|p| p.is_recorded()).collect();
+        let other_points: Vec<&PointWithId> =
+            other.points.iter().filter(|p| p.is_recorded()).collect();
+
+        if points.is_empty() || other_points.is_empty() {
+            return DuplicateAnalysis::default();
+        }
+
+        let coordinates: HashSet<(u64, u64)> = points
+            .iter()
+            .map(|p| (p.x.to_bits(), p.y.to_bits()))
+            .collect();
+        let timestamps: HashSet<i64> = points
+            .iter()
+            .map(|p| p.timestamp.timestamp_millis())
+            .collect();
+
+        let share = |count: usize| count as f64 / other_points.len() as f64;
+
+        let identical_coordinates = share(
+            other_points
+                .iter()
+                .filter(|p| coordinates.contains(&(p.x.to_bits(), p.y.to_bits())))
+                .count(),
+        );
+
+        let identical_timestamps = share(
+            other_points
+                .iter()
+                .filter(|p| timestamps.contains(&p.timestamp.timestamp_millis()))
+                .count(),
+        );
+
+        let shifted_coordinates =
+            best_aligned_mode(&moving(&other_points), &moving(&points), |p, q| {
+                (
+                    ((p.x - q.x) * OFFSET_PRECISION).round() as i64,
+                    ((p.y - q.y) * OFFSET_PRECISION).round() as i64,
+                )
+            });
+
+        let shifted_timestamps = best_aligned_mode(&other_points, &points, |p, q| {
+            (p.timestamp - q.timestamp).num_milliseconds()
+        });
+
+        DuplicateAnalysis {
+            identical_coordinates,
+            shifted_coordinates,
+            identical_timestamps,
+            shifted_timestamps,
+        }
+    }
+}
+
+impl From<&DuplicateAnalysis> for DuplicateOutput {
+    fn from(value: &DuplicateAnalysis) -> Self {
+        Self {
+            identical_coordinates: value.identical_coordinates,
+            shifted_coordinates: value.shifted_coordinates,
+            identical_timestamps: value.identical_timestamps,
+            shifted_timestamps: value.shifted_timestamps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::DuplicateConfig, test_utils::TraceBuilder};
+    use chrono::Duration;
+
+    /// Trace of 20 moving fixes, followed by `held` fixes repeating the last one.
+    fn create_trace(id: &str, dx: f64, noise: f64, offset_ms: i64, held: i64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..20 + held, |i| {
+                let k = i.min(19);
+                (
+                    2.3522 + k as f64 * 0.001 + dx + noise * ((k * 7) % 5) as f64,
+                    48.8566 + k as f64 * 0.0005 + noise * ((k * 3) % 4) as f64,
+                    Duration::milliseconds(i * 1000 + offset_ms + (i * 13) % 7),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_shifted_copy() {
+        let driver = create_trace("driver", 0.0, 0.0, 0, 0);
+        let passenger = create_trace("passenger", 0.00002, 0.0, 2_000, 0);
+
+        let analysis = driver.duplicate_analysis(&passenger);
+        let config = DuplicateConfig::default();
+
+        assert_eq!(analysis.identical_coordinates, 0.0);
+        assert_eq!(analysis.shifted_coordinates, 1.0);
+        assert_eq!(analysis.shifted_timestamps, 1.0);
+        assert!(analysis.is_duplicate(config.threshold));
+    }
+
+    #[test]
+    fn test_real_devices() {
+        let driver = create_trace("driver", 0.0, 0.0, 0, 0);
+        let passenger = create_trace("passenger", 0.00002, 0.000011, 350, 0);
+
+        let analysis = driver.duplicate_analysis(&passenger);
+        let config = DuplicateConfig::default();
+
+        assert!(!analysis.is_duplicate(config.threshold));
+    }
+
+    #[test]
+    fn test_shared_stop() {
+        // Both devices hold their last fix through a traffic jam
+        let driver = create_trace("driver", 0.0, 0.0, 0, 25);
+        let passenger = create_trace("passenger", 0.00002, 0.000011, 350, 25);
+
+        let analysis = driver.duplicate_analysis(&passenger);
+        let config = DuplicateConfig::default();
+
+        assert!(!analysis.is_duplicate(config.threshold));
+    }
+}
@@ -1,3 +1,5 @@
+use serde_json::{json, Value};
+
 #[derive(thiserror::Error, Debug)]
 pub enum JourneyValidationError {
     #[error("Missing startTime")]
@@ -15,6 +17,12 @@ pub enum JourneyValidationError {
     #[error("Too many traces")]
     TooManyTraces,
 
+    #[error("Too many passengers")]
+    TooManyPassengers,
+
+    #[error("passengerId is not one of passengerIds")]
+    AmbiguousPassengers,
+
     #[error("No common points")]
     NoCommonPoints,
 
@@ -24,21 +32,90 @@ pub enum JourneyValidationError {
     #[error("Empty {0} trace")]
     EmptyTrace(String),
 
+    #[error("Passenger trace is a copy of the driver trace")]
+    DuplicatedTrace,
+
+    #[error("Replayed {0} trace")]
+    ReplayedTrace(String, String),
+
+    #[error("Overlapping journey for {0}")]
+    OverlappingJourney(String, String),
+
+    #[error("Unreachable journey for {0}")]
+    UnreachableJourney(String, String),
+
+    #[error("Too many daily journeys for {0}")]
+    TooManyDailyJourneys(String),
+
+    #[error("Suspected role swap")]
+    SuspectedRoleSwap,
+
     #[error("Start points timestamps are too far apart")]
     StartTimeDeltaTooBig,
 
+    #[error("Clock skew between traces is too big")]
+    ClockSkewTooBig,
+
     #[error("Not in France")]
     NotInFrance,
 
+    #[error("Not a car journey")]
+    InvalidTransportMode,
+
+    #[error("Suspected spoofing on {0} trace")]
+    SuspectedSpoofing(String),
+
+    #[error("Inconsistent {0} trace")]
+    InconsistentTrace(String),
+
+    #[error("Backfilled {0} trace")]
+    BackfilledTrace(String),
+
+    #[error("Passenger trace outside the driver corridor")]
+    OutsideCorridor,
+
+    #[error("Railway journey")]
+    RailwayJourney(String),
+
+    #[error("Low confidence over the route")]
+    LowConfidenceProfile,
+
     #[error("Distance too {0}")]
     InvalidDistance(String),
 
     #[error("invalid json")]
     Serde(#[from] serde_json::Error),
 
+    #[error("invalid road network")]
+    RoadNetwork(#[from] Box<geojson::Error>),
+
+    #[error("invalid gtfs feed: {0}")]
+    Gtfs(String),
+
     #[error("error while reading json file")]
     Io(#[from] std::io::Error),
 
     #[error("unexpected error")]
     Unexpected(#[from] anyhow::Error),
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum JourneyValidationWarning {
+    #[error("Suspected spoofing on {0} trace")]
+    SuspectedSpoofing(String),
+    #[error("Suspected role swap")]
+    SuspectedRoleSwap,
+}
+
+impl JourneyValidationError {
+    /// Machine readable context attached to the cancel reason.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            Self::RailwayJourney(route_id) => Some(json!({ "routeId": route_id })),
+            Self::ReplayedTrace(_, journey_id)
+            | Self::OverlappingJourney(_, journey_id)
+            | Self::UnreachableJourney(_, journey_id) => Some(json!({ "journeyId": journey_id })),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::BTreeSet;
+
+use chrono::Duration;
+use geo::{Coord, HaversineDistance, Point};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{FingerprintConfig, ResampleStep},
+    store::JsonLinesStore,
+    trace::Trace,
+};
+
+/// Fingerprints are computed on a trace resampled every this many meters, so they do not depend
+/// on the sampling rate nor on the timestamps.
+const FINGERPRINT_STEP: f64 = 50.0;
+const SHINGLE_SIZE: usize = 3;
+/// Timelines sample the position every this many milliseconds since the first fix.
+const TIMELINE_STEP_MS: i64 = 10_000;
+/// Largest sample shift tried when aligning timelines, for replays trimmed at the start.
+const MAX_TIMELINE_SHIFT: i64 = 6;
+
+/// Set of consecutive geohash cells crossed by a trace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Fingerprint(pub BTreeSet<String>);
+
+impl Fingerprint {
+    /// Jaccard index of both shingle sets.
+    pub fn similarity_with(&self, other: &Fingerprint) -> f64 {
+        let union = self.0.union(&other.0).count();
+
+        if union == 0 {
+            return 0.0;
+        }
+
+        self.0.intersection(&other.0).count() as f64 / union as f64
+    }
+}
+
+/// Positions of a trace every `TIMELINE_STEP_MS` since its first fix. Unlike the shape, the pace
+/// along the route changes with the traffic between two journeys, but not in a replay.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Timeline(pub Vec<(f64, f64)>);
+
+impl Timeline {
+    /// Best share over all sample alignments of the samples within `radius` meters of each
+    /// other, regardless of the clock of both traces.
+    pub fn replay_ratio(&self, other: &Timeline, radius: f64) -> f64 {
+        let min_samples = (self.0.len().min(other.0.len()) / 2).max(1);
+
+        (-MAX_TIMELINE_SHIFT..=MAX_TIMELINE_SHIFT)
+            .filter_map(|shift| {
+                let distances: Vec<f64> = self
+                    .0
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, a)| {
+                        let j = usize::try_from(i as i64 + shift).ok()?;
+                        other
+                            .0
+                            .get(j)
+                            .map(|b| Point::from(*a).haversine_distance(&Point::from(*b)))
+                    })
+                    .collect();
+
+                (distances.len() >= min_samples).then(|| {
+                    distances.iter().filter(|d| **d <= radius).count() as f64
+                        / distances.len() as f64
+                })
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+impl<T> Trace<T> {
+    pub fn timeline(&self) -> Timeline {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return Timeline::default();
+        };
+
+        let samples = last.get_ms_delta_with(first) / TIMELINE_STEP_MS;
+
+        Timeline(
+            (0..=samples)
+                .filter_map(|i| {
+                    self.position_at(first.timestamp + Duration::milliseconds(i * TIMELINE_STEP_MS))
+                })
+                .map(|p| p.x_y())
+                .collect(),
+        )
+    }
+
+    pub fn fingerprint(&self, precision: usize) -> Fingerprint {
+        let mut cells: Vec<String> = self
+            .resampled(ResampleStep::Meters(FINGERPRINT_STEP))
+            .points
+            .iter()
+            .filter_map(|p| geohash::encode(Coord::from(p), precision).ok())
+            .collect();
+        cells.dedup();
+
+        if cells.len() < SHINGLE_SIZE {
+            return Fingerprint(BTreeSet::from_iter([cells.concat()]));
+        }
+
+        Fingerprint(cells.windows(SHINGLE_SIZE).map(|w| w.concat()).collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredFingerprint {
+    pub journey_id: String,
+    pub trace_id: String,
+    pub fingerprint: Fingerprint,
+    /// Missing from the fingerprints stored before timelines, which then never match.
+    #[serde(default)]
+    pub timeline: Timeline,
+}
+
+#[derive(Debug, Clone)]
+pub struct FingerprintMatch {
+    pub journey_id: String,
+    pub trace_id: String,
+    pub similarity: f64,
+    pub replay_ratio: f64,
+}
+
+/// Most similar trace submitted in another journey, with both the same shape and the same pace.
+pub fn find_match(
+    stored: &[StoredFingerprint],
+    fingerprint: &Fingerprint,
+    timeline: &Timeline,
+    journey_id: Option<&str>,
+    config: &FingerprintConfig,
+) -> Option<FingerprintMatch> {
+    stored
+        .iter()
+        .filter(|stored| Some(stored.journey_id.as_str()) != journey_id)
+        .map(|stored| FingerprintMatch {
+            similarity: stored.fingerprint.similarity_with(fingerprint),
+            replay_ratio: stored.timeline.replay_ratio(timeline, config.replay_radius),
+            journey_id: stored.journey_id.clone(),
+            trace_id: stored.trace_id.clone(),
+        })
+        .filter(|m| {
+            m.similarity >= config.min_similarity && m.replay_ratio >= config.min_replay_ratio
+        })
+        .max_by(|a, b| a.replay_ratio.total_cmp(&b.replay_ratio))
+}
+
+/// Fingerprints of every validated journey.
+pub type FingerprintStore = JsonLinesStore<StoredFingerprint>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    fn create_trace(id: &str, noise: f64, step_s: i64, offset_s: i64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..200 / step_s, |i| {
+                (
+                    2.3522 + (i * step_s) as f64 * 0.0002 + noise * ((i * 7) % 3) as f64,
+                    48.8566 + (i * step_s) as f64 * 0.0001,
+                    Duration::seconds(i * step_s + offset_s),
+                )
+            })
+            .build()
+    }
+
+    fn stored(journey_id: &str, trace: &Trace, config: &FingerprintConfig) -> StoredFingerprint {
+        StoredFingerprint {
+            journey_id: journey_id.to_string(),
+            trace_id: trace.id.clone(),
+            fingerprint: trace.fingerprint(config.precision),
+            timeline: trace.timeline(),
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_replay() {
+        let config = FingerprintConfig::default();
+        let original = create_trace("original", 0.0, 1, 0);
+        // Resubmitted the next day, downsampled with some noise
+        let replay = create_trace("replay", 0.000005, 2, 86_400);
+
+        let found = find_match(
+            &[stored("journey_1", &original, &config)],
+            &replay.fingerprint(config.precision),
+            &replay.timeline(),
+            Some("journey_2"),
+            &config,
+        )
+        .unwrap();
+
+        assert!(found.similarity >= config.min_similarity);
+        assert!(found.replay_ratio >= config.min_replay_ratio);
+    }
+
+    #[test]
+    fn test_commute_another_day() {
+        let config = FingerprintConfig::default();
+        let monday = create_trace("monday", 0.0, 1, 0);
+        // Same route the next day, 10% slower in the traffic
+        let mut tuesday = create_trace("tuesday", 0.0, 1, 86_400);
+        tuesday
+            .points
+            .iter_mut()
+            .zip(0..)
+            .for_each(|(p, i)| p.timestamp += Duration::milliseconds(i * 100));
+        let fingerprint = tuesday.fingerprint(config.precision);
+
+        let found = find_match(
+            &[stored("journey_1", &monday, &config)],
+            &fingerprint,
+            &tuesday.timeline(),
+            Some("journey_2"),
+            &config,
+        );
+
+        assert!(
+            monday
+                .fingerprint(config.precision)
+                .similarity_with(&fingerprint)
+                >= 0.9
+        );
+        assert!(found.is_none());
+    }
+
+    #[test]
+    fn test_store() {
+        let path = std::env::temp_dir().join(format!(
+            "fc_journey_validation_fingerprints_{}.jsonl",
+            std::process::id()
+        ));
+        let store = FingerprintStore::new(path.clone());
+        let config = FingerprintConfig::default();
+        let trace = create_trace("original", 0.0, 1, 0);
+
+        store
+            .append(&[stored("journey_1", &trace, &config)])
+            .unwrap();
+
+        let stored = store.load().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let (fingerprint, timeline) = (trace.fingerprint(config.precision), trace.timeline());
+        let found = find_match(&stored, &fingerprint, &timeline, Some("journey_2"), &config);
+        let own = find_match(&stored, &fingerprint, &timeline, Some("journey_1"), &config);
+
+        assert_eq!(found.unwrap().journey_id, "journey_1");
+        assert!(own.is_none());
+    }
+}
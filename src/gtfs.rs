@@ -0,0 +1,309 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use geo::{HaversineDistance, LineString, Point};
+use rstar::RTree;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    config::RailwayConfig,
+    error::JourneyValidationError,
+    spatial::{segment_index, segments_within, SegmentIndex},
+    trace::Trace,
+    Result,
+};
+
+#[derive(Deserialize)]
+struct ShapeRecord {
+    shape_id: String,
+    shape_pt_lat: f64,
+    shape_pt_lon: f64,
+    shape_pt_sequence: u32,
+}
+
+#[derive(Deserialize)]
+struct TripRecord {
+    route_id: String,
+    shape_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RouteRecord {
+    route_id: String,
+    route_type: u16,
+}
+
+#[derive(Deserialize)]
+struct StopRecord {
+    stop_lat: Option<f64>,
+    stop_lon: Option<f64>,
+}
+
+pub struct RailShape {
+    pub route_id: String,
+    pub linestring: LineString<f64>,
+}
+
+/// Rail shapes and stations of a local GTFS feed.
+pub struct RailNetwork {
+    shapes: Vec<RailShape>,
+    index: SegmentIndex,
+    stations: RTree<Point<f64>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RailMatch {
+    pub route_id: String,
+    pub ratio: f64,
+    pub speed: f64,
+    pub from_station: bool,
+    pub to_station: bool,
+}
+
+/// Metro, rail, monorail and the extended railway and urban railway types. Trams are excluded
+/// since they share the street with cars.
+fn is_rail(route_type: u16) -> bool {
+    matches!(route_type, 1 | 2 | 12 | 100..=199 | 400..=499)
+}
+
+fn read_records<T: DeserializeOwned>(path: &Path) -> Result<Option<Vec<T>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    csv::Reader::from_path(path)
+        .and_then(|mut reader| reader.deserialize().collect())
+        .map(Some)
+        .map_err(|err| JourneyValidationError::Gtfs(err.to_string()))
+}
+
+impl RailNetwork {
+    /// Loads `shapes.txt` and `stops.txt` from a GTFS directory. When present, `trips.txt` and
+    /// `routes.txt` map shapes to their route and keep only the rail ones.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let shapes: Vec<ShapeRecord> = read_records(&dir.join("shapes.txt"))?
+            .ok_or_else(|| JourneyValidationError::Gtfs("missing shapes.txt".into()))?;
+        let trips: Vec<TripRecord> = read_records(&dir.join("trips.txt"))?.unwrap_or_default();
+        let routes: Option<Vec<RouteRecord>> = read_records(&dir.join("routes.txt"))?;
+        let stops: Vec<StopRecord> = read_records(&dir.join("stops.txt"))?.unwrap_or_default();
+
+        let rail_routes: Option<HashSet<String>> = routes.map(|routes| {
+            routes
+                .into_iter()
+                .filter(|r| is_rail(r.route_type))
+                .map(|r| r.route_id)
+                .collect()
+        });
+
+        let shape_routes: HashMap<String, String> = trips
+            .into_iter()
+            .filter_map(|t| t.shape_id.map(|shape_id| (shape_id, t.route_id)))
+            .collect();
+
+        let mut points: BTreeMap<String, Vec<(u32, Point<f64>)>> = BTreeMap::new();
+
+        for shape in shapes {
+            points.entry(shape.shape_id).or_default().push((
+                shape.shape_pt_sequence,
+                Point::new(shape.shape_pt_lon, shape.shape_pt_lat),
+            ));
+        }
+
+        let shapes: Vec<RailShape> = points
+            .into_iter()
+            .filter_map(|(shape_id, mut points)| {
+                let route_id = shape_routes.get(&shape_id).cloned().unwrap_or(shape_id);
+
+                if let Some(rail_routes) = &rail_routes {
+                    if !rail_routes.contains(&route_id) {
+                        return None;
+                    }
+                }
+
+                points.sort_by_key(|(sequence, _)| *sequence);
+
+                Some(RailShape {
+                    route_id,
+                    linestring: points.into_iter().map(|(_, p)| p).collect(),
+                })
+            })
+            .collect();
+
+        let stations = RTree::bulk_load(
+            stops
+                .into_iter()
+                .filter_map(|s| Some(Point::new(s.stop_lon?, s.stop_lat?)))
+                .collect(),
+        );
+
+        Ok(Self::new(shapes, stations))
+    }
+
+    pub fn new(shapes: Vec<RailShape>, stations: RTree<Point<f64>>) -> Self {
+        let index = segment_index(shapes.iter().map(|s| &s.linestring).enumerate());
+
+        Self {
+            shapes,
+            index,
+            stations,
+        }
+    }
+
+    fn is_near_station(&self, point: Point<f64>, radius: f64) -> bool {
+        self.stations
+            .nearest_neighbor(&point)
+            .is_some_and(|station| station.haversine_distance(&point) <= radius)
+    }
+
+    /// Finds the rail shape followed by the trace between `start` and `end`, if the share of
+    /// points close to it and the speed look like a train. Journeys between two stations only
+    /// need to reach the lower station speed.
+    pub fn match_trace<T>(
+        &self,
+        trace: &Trace<T>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        config: &RailwayConfig,
+    ) -> Option<RailMatch> {
+        let points: Vec<_> = trace
+            .points
+            .iter()
+            .filter(|p| p.timestamp >= start && p.timestamp <= end)
+            .collect();
+
+        let (first, last) = (points.first()?, points.last()?);
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+
+        for point in &points {
+            let mut shapes: Vec<usize> =
+                segments_within(&self.index, Point::from(*point), config.max_distance)
+                    .map(|(segment, _, _)| segment.data)
+                    .collect();
+            shapes.sort_unstable();
+            shapes.dedup();
+
+            for shape in shapes {
+                *counts.entry(shape).or_insert(0) += 1;
+            }
+        }
+
+        let (shape, count) = counts
+            .into_iter()
+            .max_by_key(|(shape, count)| (*count, usize::MAX - shape))?;
+        let ratio = count as f64 / points.len() as f64;
+
+        let distance: f64 = points
+            .windows(2)
+            .map(|w| Point::from(w[0]).haversine_distance(&Point::from(w[1])))
+            .sum();
+        let seconds = last.get_ms_delta_with(first) as f64 / 1000.0;
+        let speed = if seconds > 0.0 {
+            distance / seconds
+        } else {
+            0.0
+        };
+
+        let from_station = self.is_near_station(Point::from(*first), config.station_radius);
+        let to_station = self.is_near_station(Point::from(*last), config.station_radius);
+
+        let min_speed = if from_station && to_station {
+            config.station_min_speed
+        } else {
+            config.min_speed
+        };
+
+        if ratio < config.min_ratio || speed < min_speed {
+            return None;
+        }
+
+        Some(RailMatch {
+            route_id: self.shapes[shape].route_id.clone(),
+            ratio,
+            speed,
+            from_station,
+            to_station,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    fn create_network() -> RailNetwork {
+        RailNetwork::new(
+            vec![RailShape {
+                route_id: "TER_1".to_string(),
+                linestring: LineString::from(vec![(2.30, 48.80), (2.40, 48.80)]),
+            }],
+            RTree::bulk_load(vec![Point::new(2.30, 48.80), Point::new(2.40, 48.80)]),
+        )
+    }
+
+    /// Trace heading east from one station to the other, ~730 meters every `step_s` seconds.
+    fn create_trace(y: f64, step_s: i64) -> Trace {
+        TraceBuilder::new("trace_1")
+            .fixes(0..=10, |i| {
+                (2.30 + i as f64 * 0.01, y, Duration::seconds(i * step_s))
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_match_trace_on_rail() {
+        let network = create_network();
+        let trace = create_trace(48.8001, 24);
+        let (start, end) = trace.get_edges();
+
+        let rail_match = network
+            .match_trace(
+                &trace,
+                start.timestamp,
+                end.timestamp,
+                &RailwayConfig::default(),
+            )
+            .unwrap();
+
+        assert_eq!(rail_match.route_id, "TER_1");
+        assert_eq!(rail_match.ratio, 1.0);
+        assert!(rail_match.from_station && rail_match.to_station);
+    }
+
+    #[test]
+    fn test_match_trace_off_rail() {
+        let network = create_network();
+        let trace = create_trace(48.81, 24);
+        let (start, end) = trace.get_edges();
+
+        assert!(network
+            .match_trace(
+                &trace,
+                start.timestamp,
+                end.timestamp,
+                &RailwayConfig::default(),
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_match_road_along_rail() {
+        let network = create_network();
+        // Carpool meeting at a station and driving at ~73 km/h on a road along the track
+        let trace = create_trace(48.8002, 36);
+        let (start, end) = trace.get_edges();
+
+        assert!(network
+            .match_trace(
+                &trace,
+                start.timestamp,
+                end.timestamp,
+                &RailwayConfig::default(),
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::{DateTime, Utc};
+use geo::{HaversineDistance, Point};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::HistoryConfig, store::JsonLinesStore, trace::Trace};
+
+/// Slack on the distance between two journeys, their edges are only known within the GPS noise.
+const REACH_TOLERANCE: f64 = 1_000.0;
+
+/// Time span and edges of a journey from the point of view of one of its users.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserJourney {
+    pub journey_id: String,
+    pub user_id: String,
+    pub role: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub start_latitude: f64,
+    pub start_longitude: f64,
+    pub end_latitude: f64,
+    pub end_longitude: f64,
+}
+
+/// Journeys of every user, keyed by `user_id`.
+pub type JourneyStore = JsonLinesStore<UserJourney>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    Overlapping(String),
+    Unreachable(String),
+    DailyCap,
+}
+
+impl UserJourney {
+    pub fn new<T>(journey_id: &str, user_id: &str, role: &str, trace: &Trace<T>) -> Self {
+        let (start, end) = trace.get_edges();
+
+        Self {
+            journey_id: journey_id.to_string(),
+            user_id: user_id.to_string(),
+            role: role.to_string(),
+            start_time: start.timestamp,
+            end_time: end.timestamp,
+            start_latitude: start.y,
+            start_longitude: start.x,
+            end_latitude: end.y,
+            end_longitude: end.x,
+        }
+    }
+
+    fn start(&self) -> Point {
+        Point::new(self.start_longitude, self.start_latitude)
+    }
+
+    fn end(&self) -> Point {
+        Point::new(self.end_longitude, self.end_latitude)
+    }
+
+    fn overlaps(&self, other: &UserJourney) -> bool {
+        self.start_time < other.end_time && other.start_time < self.end_time
+    }
+
+    /// Whether the user could travel from the end of this journey to the start of the next one.
+    fn reaches(&self, next: &UserJourney, max_speed: f64) -> bool {
+        let seconds = (next.start_time - self.end_time).num_milliseconds() as f64 / 1000.0;
+
+        self.end().haversine_distance(&next.start()) <= max_speed * seconds + REACH_TOLERANCE
+    }
+
+    /// First conflict between this journey and the other journeys of the same user.
+    pub fn find_conflict(
+        &self,
+        history: &[UserJourney],
+        config: &HistoryConfig,
+    ) -> Option<Conflict> {
+        let others: Vec<&UserJourney> = history
+            .iter()
+            .filter(|j| j.user_id == self.user_id && j.journey_id != self.journey_id)
+            .collect();
+
+        for other in &others {
+            if self.overlaps(other) {
+                return Some(Conflict::Overlapping(other.journey_id.clone()));
+            }
+
+            let reachable = if other.end_time <= self.start_time {
+                other.reaches(self, config.max_speed)
+            } else {
+                self.reaches(other, config.max_speed)
+            };
+
+            if !reachable {
+                return Some(Conflict::Unreachable(other.journey_id.clone()));
+            }
+        }
+
+        let day = |journey: &UserJourney| {
+            journey
+                .start_time
+                .with_timezone(&config.timezone)
+                .date_naive()
+        };
+        let same_day = others.iter().filter(|j| day(j) == day(self)).count();
+
+        if config
+            .max_daily_journeys
+            .is_some_and(|max| same_day + 1 > max)
+        {
+            return Some(Conflict::DailyCap);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn create_journey(id: &str, start_hour: u32, duration_min: i64, lon: f64) -> UserJourney {
+        let start_time = Utc.with_ymd_and_hms(2024, 6, 18, start_hour, 0, 0).unwrap();
+
+        UserJourney {
+            journey_id: id.to_string(),
+            user_id: "user_1".to_string(),
+            role: "driver".to_string(),
+            start_time,
+            end_time: start_time + Duration::minutes(duration_min),
+            start_latitude: 48.8566,
+            start_longitude: lon,
+            end_latitude: 48.8566,
+            end_longitude: lon + 0.1,
+        }
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let config = HistoryConfig {
+            max_daily_journeys: Some(2),
+            ..Default::default()
+        };
+        let history = vec![create_journey("morning", 8, 30, 2.35)];
+
+        let overlapping = create_journey("overlapping", 8, 20, 2.35);
+        // 3° of longitude (~220 km) away, 30 minutes after the previous journey
+        let unreachable = create_journey("unreachable", 9, 30, 5.45);
+        let evening = create_journey("evening", 18, 30, 2.45);
+
+        assert_eq!(
+            overlapping.find_conflict(&history, &config),
+            Some(Conflict::Overlapping("morning".into()))
+        );
+        assert_eq!(
+            unreachable.find_conflict(&history, &config),
+            Some(Conflict::Unreachable("morning".into()))
+        );
+        assert_eq!(evening.find_conflict(&history, &config), None);
+
+        let history = vec![history[0].clone(), evening.clone()];
+        // 22:00 and 00:00 in Paris
+        let night = create_journey("night", 20, 30, 2.55);
+        let after_midnight = create_journey("after_midnight", 22, 30, 2.55);
+
+        assert_eq!(
+            night.find_conflict(&history, &config),
+            Some(Conflict::DailyCap)
+        );
+        assert_eq!(after_midnight.find_conflict(&history, &config), None);
+    }
+}
@@ -11,10 +11,13 @@ use crate::{error::JourneyValidationError, Result};
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct JourneyInput {
+    pub id: Option<String>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub driver_id: Option<String>,
     pub passenger_id: Option<String>,
+    /// Passengers of a vanpool, each one validated against the driver.
+    pub passenger_ids: Option<Vec<String>>,
     pub gps_trace: Vec<TraceInput>,
 }
 
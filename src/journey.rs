@@ -1,38 +1,190 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
 use crate::{
-    error::JourneyValidationError,
-    input::JourneyInput,
-    output::{Output, TracesOutput},
+    common,
+    config::Config,
+    error::{JourneyValidationError, JourneyValidationWarning},
+    fingerprint::{find_match, FingerprintStore, StoredFingerprint},
+    gtfs::RailNetwork,
+    history::{Conflict, JourneyStore, UserJourney},
+    input::{JourneyInput, TraceInput},
+    map_matching::RoadNetwork,
+    mode,
+    output::{
+        ConsistenciesOutput, CorridorsOutput, FragmentsOutput, LatenciesOutput, MapMatchingOutput,
+        NormalizationOutput, Output, RoleSwapOutput, SpoofingAnalysisOutput, StopsOutput,
+        TracesOutput, TransportModesOutput,
+    },
+    profile::{self, ConfidenceSegment},
+    role,
+    skew::ClockSkew,
     trace::{CommonTrace, Trace},
     Result,
 };
 
 const MAX_DELTA_IN_MILLISECONDS: i64 = 90_000;
-const SIMPLIFY_EPSILON: f64 = 0.00001;
 const MAX_DISTANCE: f64 = 80_000.0;
 const MIN_DISTANCE: f64 = 2_000.0;
 
 pub struct Journey {
+    pub id: Option<String>,
+    pub driver_id: String,
+    pub passenger_id: String,
     pub driver_trace: Trace,
     pub passenger_trace: Trace,
+    /// Number of traces merged into each trace.
+    pub fragments: FragmentsOutput,
+    /// Corrections made to the input points of each trace.
+    pub normalization: NormalizationOutput,
+    pub config: Config,
+    pub networks: Arc<Networks>,
+}
+
+/// Road and rail networks of the optional rules, loaded once and shared by the passengers of a
+/// carpool.
+#[derive(Default)]
+pub struct Networks {
+    pub road: Option<RoadNetwork>,
+    pub rail: Option<RailNetwork>,
+}
+
+impl Networks {
+    pub fn load(config: &Config) -> Result<Self> {
+        Ok(Self {
+            road: config
+                .map_matching
+                .as_ref()
+                .map(|config| RoadNetwork::from_path(&config.road_network_path))
+                .transpose()?,
+            rail: config
+                .railway
+                .as_ref()
+                .map(|config| RailNetwork::from_dir(&config.gtfs_path))
+                .transpose()?,
+        })
+    }
 }
 
 impl Journey {
     pub fn validate(&self) -> Output {
-        if let Err(err) = self.validate_edges() {
-            return Output::from(err);
+        let skew_config = self.config.clock_skew.as_ref();
+        let mut clock_skew = skew_config.and_then(|config| {
+            ClockSkew::estimate(&self.driver_trace, &self.passenger_trace, config)
+        });
+
+        let shifted_passenger_trace;
+        let passenger_trace = match clock_skew.as_mut() {
+            Some(skew) if skew_config.is_some_and(|config| config.apply) => {
+                if skew.implausible {
+                    return Output::from(JourneyValidationError::ClockSkewTooBig);
+                }
+
+                skew.applied = true;
+                shifted_passenger_trace = self.passenger_trace.shifted(skew.offset_ms);
+                &shifted_passenger_trace
+            }
+            _ => &self.passenger_trace,
+        };
+
+        let mut warnings = vec![];
+
+        let role_swap = match self.validate_role_swap(passenger_trace, &mut warnings) {
+            Ok(role_swap) => role_swap,
+            Err(err) => return Output::from(err),
+        };
+
+        match Self::validate_traces_edges(&self.driver_trace, passenger_trace) {
+            // A swapped passenger trace starts well before the driver one, the swap explains it
+            Err(JourneyValidationError::StartTimeDeltaTooBig) if role_swap.suspected => {
+                return Output::from(JourneyValidationError::SuspectedRoleSwap);
+            }
+            Err(err) => return Output::from(err),
+            Ok(()) => {}
         }
 
+        let duplicate = self.driver_trace.duplicate_analysis(&self.passenger_trace);
+
+        if duplicate.is_duplicate(self.config.duplicate.threshold) {
+            return Output::from(JourneyValidationError::DuplicatedTrace);
+        }
+
+        let fingerprints = match self.validate_fingerprints() {
+            Ok(fingerprints) => fingerprints,
+            Err(err) => return Output::from(err),
+        };
+
+        let user_journeys = match self.validate_history() {
+            Ok(user_journeys) => user_journeys,
+            Err(err) => return Output::from(err),
+        };
+
+        let spoofing = match self.validate_spoofing(&mut warnings) {
+            Ok(spoofing) => spoofing,
+            Err(err) => return Output::from(err),
+        };
+
+        let consistency = match self.validate_consistency() {
+            Ok(consistency) => consistency,
+            Err(err) => return Output::from(err),
+        };
+
+        let upload_latency = match self.validate_upload_latency() {
+            Ok(upload_latency) => upload_latency,
+            Err(err) => return Output::from(err),
+        };
+
+        let pickup_drop_off =
+            passenger_trace.pickup_drop_off(&self.driver_trace, &self.config.pickup);
+        let pickup_point = pickup_drop_off.as_ref().map(|p| p.pickup.into());
+        let drop_off_point = pickup_drop_off.as_ref().map(|p| p.drop_off.into());
+        let detour = pickup_drop_off
+            .as_ref()
+            .map(|p| (&self.driver_trace.detour(p)).into());
+
+        let corridor = match self.validate_corridor() {
+            Ok(corridor) => corridor,
+            Err(err) => return Output::from(err),
+        };
+
+        // Resampled traces are only compared, the outputs describe the recorded ones
+        let resampled_traces;
+        let (compared_driver_trace, compared_passenger_trace) = match self.config.resample {
+            Some(step) => {
+                resampled_traces = (
+                    self.driver_trace.resampled(step),
+                    passenger_trace.resampled(step),
+                );
+                (&resampled_traces.0, &resampled_traces.1)
+            }
+            None => (&self.driver_trace, passenger_trace),
+        };
+
+        let similarity = self.config.similarity.as_ref().map(|config| {
+            (&compared_driver_trace.similarity_with(compared_passenger_trace, config)).into()
+        });
+
         let CommonTrace {
             common_distance,
             common_start_point,
             common_end_point,
-        } = match self.driver_trace.common_trace_with(&self.passenger_trace) {
+        } = match common::strategy(&self.config.common_segment)
+            .common_segment(compared_driver_trace, compared_passenger_trace)
+        {
             Ok(common_trace) => common_trace,
             Err(err) => return Output::from(err),
         };
 
-        let driver_trace = self.driver_trace.simplified(SIMPLIFY_EPSILON);
-        let passenger_trace = self.passenger_trace.simplified(SIMPLIFY_EPSILON);
+        let confidence_profile = compared_driver_trace.confidence_profile(
+            compared_passenger_trace,
+            common_start_point.timestamp,
+            common_end_point.timestamp,
+            &self.config.confidence_profile,
+        );
+
+        let driver_trace = self.driver_trace.simplified_with(&self.config.simplify);
+        let passenger_trace = passenger_trace.simplified_with(&self.config.simplify);
         let average_confidence = driver_trace.confidence_with(&passenger_trace);
 
         if common_distance < MIN_DISTANCE {
@@ -43,8 +195,53 @@ impl Journey {
             return Output::from(JourneyValidationError::InvalidDistance("long".into()));
         }
 
+        if let Err(err) = self.validate_confidence_profile(&confidence_profile) {
+            return Output::from(err);
+        }
+
+        let transport_modes = match self
+            .validate_transport_modes(common_start_point.timestamp, common_end_point.timestamp)
+        {
+            Ok(transport_modes) => transport_modes,
+            Err(err) => return Output::from(err),
+        };
+
+        if let Err(err) =
+            self.validate_railway(common_start_point.timestamp, common_end_point.timestamp)
+        {
+            return Output::from(err);
+        }
+
+        let map_matching = self.map_match();
+
+        if let Err(err) = self.record_fingerprints(&fingerprints) {
+            return Output::from(err);
+        }
+
+        if let Err(err) = self.record_history(&user_journeys) {
+            return Output::from(err);
+        }
+
         Output::Success(crate::output::OutputSuccess {
             average_confidence,
+            confidence_profile: confidence_profile.iter().map(Into::into).collect(),
+            clock_skew: clock_skew.as_ref().map(Into::into),
+            map_matching,
+            stops: StopsOutput {
+                driver_trace: self.driver_trace.stops_output(&self.config.stops),
+                passenger_trace: self.passenger_trace.stops_output(&self.config.stops),
+            },
+            transport_modes,
+            spoofing,
+            consistency,
+            upload_latency,
+            corridor,
+            similarity,
+            fragments: self.fragments.clone(),
+            normalization: self.normalization.clone(),
+            duplicate: (&duplicate).into(),
+            role_swap,
+            warnings,
             traces: TracesOutput {
                 passenger_trace: passenger_trace.into(),
                 driver_trace: driver_trace.into(),
@@ -52,13 +249,311 @@ impl Journey {
             common_distance,
             common_start_point,
             common_end_point,
+            pickup_point,
+            drop_off_point,
+            detour,
         })
     }
 
-    pub fn validate_edges(&self) -> Result<()> {
+    /// Fingerprints of both traces to record once this journey is validated, so a rejected
+    /// journey does not turn its honest resubmission into a replay.
+    fn validate_fingerprints(&self) -> Result<Vec<StoredFingerprint>> {
+        let Some(config) = &self.config.fingerprint else {
+            return Ok(vec![]);
+        };
+
+        let stored = FingerprintStore::new(config.store_path.clone()).load()?;
+        let mut fingerprints = vec![];
+
+        for (role, trace) in [
+            ("driver", &self.driver_trace),
+            ("passenger", &self.passenger_trace),
+        ] {
+            let fingerprint = trace.fingerprint(config.precision);
+            let timeline = trace.timeline();
+
+            if let Some(found) =
+                find_match(&stored, &fingerprint, &timeline, self.id.as_deref(), config)
+            {
+                return Err(JourneyValidationError::ReplayedTrace(
+                    role.into(),
+                    found.journey_id,
+                ));
+            }
+
+            if let Some(journey_id) = self.id.as_ref().filter(|id| {
+                !stored
+                    .iter()
+                    .any(|s| &&s.journey_id == id && s.trace_id == trace.id)
+            }) {
+                fingerprints.push(StoredFingerprint {
+                    journey_id: journey_id.clone(),
+                    trace_id: trace.id.clone(),
+                    fingerprint,
+                    timeline,
+                });
+            }
+        }
+
+        Ok(fingerprints)
+    }
+
+    fn record_fingerprints(&self, fingerprints: &[StoredFingerprint]) -> Result<()> {
+        match &self.config.fingerprint {
+            Some(config) if !fingerprints.is_empty() => {
+                FingerprintStore::new(config.store_path.clone()).append(fingerprints)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Journeys of both users to record once this one is validated.
+    fn validate_history(&self) -> Result<Vec<UserJourney>> {
+        let (Some(config), Some(journey_id)) = (&self.config.history, &self.id) else {
+            return Ok(vec![]);
+        };
+
+        let history = JourneyStore::new(config.store_path.clone()).load()?;
+        let mut user_journeys = vec![];
+
+        for (role, user_id, trace) in [
+            ("driver", &self.driver_id, &self.driver_trace),
+            ("passenger", &self.passenger_id, &self.passenger_trace),
+        ] {
+            let user_journey = UserJourney::new(journey_id, user_id, role, trace);
+
+            match user_journey.find_conflict(&history, config) {
+                Some(Conflict::Overlapping(other)) => {
+                    return Err(JourneyValidationError::OverlappingJourney(
+                        role.into(),
+                        other,
+                    ))
+                }
+                Some(Conflict::Unreachable(other)) => {
+                    return Err(JourneyValidationError::UnreachableJourney(
+                        role.into(),
+                        other,
+                    ))
+                }
+                Some(Conflict::DailyCap) => {
+                    return Err(JourneyValidationError::TooManyDailyJourneys(role.into()))
+                }
+                None => {}
+            }
+
+            if !history
+                .iter()
+                .any(|j| &j.journey_id == journey_id && &j.user_id == user_id)
+            {
+                user_journeys.push(user_journey);
+            }
+        }
+
+        Ok(user_journeys)
+    }
+
+    fn record_history(&self, user_journeys: &[UserJourney]) -> Result<()> {
+        match &self.config.history {
+            Some(config) if !user_journeys.is_empty() => {
+                JourneyStore::new(config.store_path.clone()).append(user_journeys)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs before the edges rule, which reports the swaps flagged here rather than their start
+    /// delta.
+    fn validate_role_swap(
+        &self,
+        passenger_trace: &Trace,
+        warnings: &mut Vec<String>,
+    ) -> Result<RoleSwapOutput> {
+        let config = &self.config.role_swap;
+        let role_swap = role::role_swap_analysis(&self.driver_trace, passenger_trace, config);
+
+        if role_swap.suspected {
+            if config.reject {
+                return Err(JourneyValidationError::SuspectedRoleSwap);
+            }
+
+            warnings.push(JourneyValidationWarning::SuspectedRoleSwap.to_string());
+        }
+
+        Ok((&role_swap).into())
+    }
+
+    fn validate_spoofing(&self, warnings: &mut Vec<String>) -> Result<SpoofingAnalysisOutput> {
+        let config = &self.config.spoofing;
+        let driver_analysis = self.driver_trace.spoofing_analysis();
+        let passenger_analysis = self.passenger_trace.spoofing_analysis();
+
+        for (role, analysis) in [
+            ("driver", &driver_analysis),
+            ("passenger", &passenger_analysis),
+        ] {
+            if analysis.reaches(config.reject_score) {
+                return Err(JourneyValidationError::SuspectedSpoofing(role.into()));
+            }
+
+            if analysis.reaches(config.review_score) {
+                warnings.push(JourneyValidationWarning::SuspectedSpoofing(role.into()).to_string());
+            }
+        }
+
+        Ok(SpoofingAnalysisOutput {
+            driver_trace: (&driver_analysis).into(),
+            passenger_trace: (&passenger_analysis).into(),
+        })
+    }
+
+    fn validate_consistency(&self) -> Result<ConsistenciesOutput> {
+        let config = &self.config.consistency;
+        let driver_consistency = self.driver_trace.consistency();
+        let passenger_consistency = self.passenger_trace.consistency();
+        let below = |ratio: Option<f64>, min: Option<f64>| {
+            ratio.zip(min).is_some_and(|(ratio, min)| ratio < min)
+        };
+
+        for (role, consistency) in [
+            ("driver", &driver_consistency),
+            ("passenger", &passenger_consistency),
+        ] {
+            if below(consistency.speed_ratio, config.min_speed_ratio)
+                || below(consistency.heading_ratio, config.min_heading_ratio)
+            {
+                return Err(JourneyValidationError::InconsistentTrace(role.into()));
+            }
+        }
+
+        Ok(ConsistenciesOutput {
+            driver_trace: (&driver_consistency).into(),
+            passenger_trace: (&passenger_consistency).into(),
+        })
+    }
+
+    fn validate_upload_latency(&self) -> Result<LatenciesOutput> {
+        let config = &self.config.latency;
+        let driver_latency = self.driver_trace.upload_latency(config);
+        let passenger_latency = self.passenger_trace.upload_latency(config);
+
+        for (role, latency) in [
+            ("driver", &driver_latency),
+            ("passenger", &passenger_latency),
+        ] {
+            if config
+                .max_backfill_ratio
+                .is_some_and(|max| latency.backfill_ratio > max)
+            {
+                return Err(JourneyValidationError::BackfilledTrace(role.into()));
+            }
+        }
+
+        Ok(LatenciesOutput {
+            driver_trace: (&driver_latency).into(),
+            passenger_trace: (&passenger_latency).into(),
+        })
+    }
+
+    fn validate_corridor(&self) -> Result<CorridorsOutput> {
+        let config = &self.config.corridor;
+        let driver_overlap = self
+            .driver_trace
+            .corridor_overlap(&self.passenger_trace, config);
+        let passenger_overlap = self
+            .passenger_trace
+            .corridor_overlap(&self.driver_trace, config);
+
+        if config
+            .min_passenger_ratio
+            .is_some_and(|min| passenger_overlap.ratio < min)
+        {
+            return Err(JourneyValidationError::OutsideCorridor);
+        }
+
+        Ok(CorridorsOutput {
+            driver_trace: (&driver_overlap).into(),
+            passenger_trace: (&passenger_overlap).into(),
+        })
+    }
+
+    fn validate_confidence_profile(&self, profile: &[ConfidenceSegment]) -> Result<()> {
+        let config = &self.config.confidence_profile;
+
+        match config.min_confidence {
+            Some(min_confidence)
+                if profile::confident_share(profile, min_confidence) < config.min_share =>
+            {
+                Err(JourneyValidationError::LowConfidenceProfile)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_transport_modes(
+        &self,
+        common_start: DateTime<Utc>,
+        common_end: DateTime<Utc>,
+    ) -> Result<TransportModesOutput> {
+        let common_car_ratio =
+            mode::car_ratio(&self.driver_trace.mode_distances(common_start, common_end));
+
+        // Without classified moving distance there is nothing to hold against the journey
+        if let (Some(car_ratio), Some(min_car_ratio)) =
+            (common_car_ratio, self.config.transport_mode.min_car_ratio)
+        {
+            if car_ratio < min_car_ratio {
+                return Err(JourneyValidationError::InvalidTransportMode);
+            }
+        }
+
         let (driver_start, driver_end) = self.driver_trace.get_edges();
         let (passenger_start, passenger_end) = self.passenger_trace.get_edges();
 
+        Ok(TransportModesOutput {
+            common_car_ratio,
+            driver_trace: self
+                .driver_trace
+                .mode_distances(driver_start.timestamp, driver_end.timestamp),
+            passenger_trace: self
+                .passenger_trace
+                .mode_distances(passenger_start.timestamp, passenger_end.timestamp),
+        })
+    }
+
+    fn validate_railway(
+        &self,
+        common_start: DateTime<Utc>,
+        common_end: DateTime<Utc>,
+    ) -> Result<()> {
+        let (Some(config), Some(network)) = (&self.config.railway, &self.networks.rail) else {
+            return Ok(());
+        };
+
+        match network.match_trace(&self.driver_trace, common_start, common_end, config) {
+            Some(rail_match) => Err(JourneyValidationError::RailwayJourney(rail_match.route_id)),
+            None => Ok(()),
+        }
+    }
+
+    fn map_match(&self) -> Option<MapMatchingOutput> {
+        let (Some(config), Some(network)) = (&self.config.map_matching, &self.networks.road) else {
+            return None;
+        };
+
+        Some(MapMatchingOutput {
+            driver_trace: (&network.match_trace(&self.driver_trace, config)).into(),
+            passenger_trace: (&network.match_trace(&self.passenger_trace, config)).into(),
+        })
+    }
+
+    pub fn validate_edges(&self) -> Result<()> {
+        Self::validate_traces_edges(&self.driver_trace, &self.passenger_trace)
+    }
+
+    fn validate_traces_edges(driver_trace: &Trace, passenger_trace: &Trace) -> Result<()> {
+        let (driver_start, driver_end) = driver_trace.get_edges();
+        let (passenger_start, passenger_end) = passenger_trace.get_edges();
+
         if driver_start.get_ms_delta_with(passenger_start) > MAX_DELTA_IN_MILLISECONDS {
             return Err(JourneyValidationError::StartTimeDeltaTooBig);
         }
@@ -72,12 +567,20 @@ impl Journey {
 
         Ok(())
     }
-}
 
-impl TryFrom<JourneyInput> for Journey {
-    type Error = JourneyValidationError;
+    pub fn new(journey: JourneyInput, config: Config) -> Result<Self> {
+        let networks = Arc::new(Networks::load(&config)?);
 
-    fn try_from(journey: JourneyInput) -> Result<Self, Self::Error> {
+        Self::with_passenger(&journey, journey.passenger_id.as_ref(), config, networks)
+    }
+
+    /// Pairs the driver with one of the passengers of the journey.
+    pub(crate) fn with_passenger(
+        journey: &JourneyInput,
+        passenger_id: Option<&String>,
+        config: Config,
+        networks: Arc<Networks>,
+    ) -> Result<Self> {
         journey
             .start_time
             .ok_or(JourneyValidationError::MissingStartTime)?;
@@ -91,26 +594,17 @@ impl TryFrom<JourneyInput> for Journey {
             .as_ref()
             .ok_or(JourneyValidationError::MissingDriver)?;
 
-        let passenger_id = journey
-            .passenger_id
-            .as_ref()
-            .ok_or(JourneyValidationError::MissingPassenger)?;
+        let passenger_id = passenger_id.ok_or(JourneyValidationError::MissingPassenger)?;
 
         if passenger_id == driver_id {
             return Err(JourneyValidationError::InvalidPassenger);
         }
 
-        let driver_trace = journey
-            .gps_trace
-            .iter()
-            .find(|t| t.user_id.as_ref() == Some(driver_id))
-            .ok_or(JourneyValidationError::MissingTrace("driver".into()))?;
+        let driver_fragments = Self::fragments_of(journey, driver_id, "driver", &config)?;
+        let passenger_fragments = Self::fragments_of(journey, passenger_id, "passenger", &config)?;
 
-        let passenger_trace = journey
-            .gps_trace
-            .iter()
-            .find(|t| t.user_id.as_ref() == Some(passenger_id))
-            .ok_or(JourneyValidationError::MissingTrace("passenger".into()))?;
+        let (driver_trace, driver_normalization) = Trace::normalized(&driver_fragments);
+        let (passenger_trace, passenger_normalization) = Trace::normalized(&passenger_fragments);
 
         if driver_trace.points.len() < 2 {
             return Err(JourneyValidationError::EmptyTrace("driver".into()));
@@ -121,8 +615,143 @@ impl TryFrom<JourneyInput> for Journey {
         }
 
         Ok(Self {
-            driver_trace: driver_trace.into(),
-            passenger_trace: passenger_trace.into(),
+            id: journey.id.clone(),
+            driver_id: driver_id.clone(),
+            passenger_id: passenger_id.clone(),
+            driver_trace,
+            passenger_trace,
+            fragments: FragmentsOutput {
+                driver_trace: driver_fragments.len(),
+                passenger_trace: passenger_fragments.len(),
+            },
+            normalization: NormalizationOutput::new(
+                &driver_normalization,
+                &passenger_normalization,
+            ),
+            config,
+            networks,
         })
     }
+
+    fn fragments_of<'a>(
+        journey: &'a JourneyInput,
+        user_id: &String,
+        role: &str,
+        config: &Config,
+    ) -> Result<Vec<&'a TraceInput>> {
+        let fragments: Vec<&TraceInput> = journey
+            .gps_trace
+            .iter()
+            .filter(|t| t.user_id.as_ref() == Some(user_id))
+            .collect();
+
+        if fragments.is_empty() {
+            return Err(JourneyValidationError::MissingTrace(role.into()));
+        }
+
+        if fragments.len() > config.max_fragments.0 {
+            return Err(JourneyValidationError::TooManyTraces);
+        }
+
+        Ok(fragments)
+    }
+}
+
+impl TryFrom<JourneyInput> for Journey {
+    type Error = JourneyValidationError;
+
+    fn try_from(journey: JourneyInput) -> Result<Self, Self::Error> {
+        Self::new(journey, Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ResampleStep, normalize::Normalization, test_utils::eastward_trace};
+    use chrono::Duration;
+
+    fn create_journey(driver_trace: Trace, mut passenger_trace: Trace, config: Config) -> Journey {
+        // Two devices in the same car never record the exact same fixes
+        passenger_trace
+            .points
+            .iter_mut()
+            .zip(0..)
+            .for_each(|(p, i)| {
+                p.y += ((i * 7) % 5) as f64 * 0.0000123;
+                p.timestamp += Duration::milliseconds(300 + (i * 13) % 7);
+            });
+
+        Journey {
+            id: None,
+            driver_id: "driver".to_string(),
+            passenger_id: "passenger".to_string(),
+            driver_trace,
+            passenger_trace,
+            fragments: FragmentsOutput {
+                driver_trace: 1,
+                passenger_trace: 1,
+            },
+            normalization: NormalizationOutput::new(
+                &Normalization::default(),
+                &Normalization::default(),
+            ),
+            config,
+            networks: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_role_swap_rejected() {
+        // The declared passenger drove to the pickup point and kept driving after the drop-off
+        let journey = create_journey(
+            eastward_trace("driver", 5, 15, 0.0),
+            eastward_trace("passenger", 0, 20, 0.0),
+            Config::default(),
+        );
+
+        // The start times are too far apart, the suspected swap is the reported reason
+        match journey.validate() {
+            Output::Error(err) => {
+                assert_eq!(
+                    err.cancel_reason,
+                    JourneyValidationError::SuspectedRoleSwap.to_string()
+                );
+            }
+            _ => panic!("expected a rejection"),
+        }
+    }
+
+    #[test]
+    fn test_resampled_outputs() {
+        let traces = |config: Config| {
+            let journey = create_journey(
+                eastward_trace("driver", 0, 20, 0.0),
+                eastward_trace("passenger", 0, 20, 0.0),
+                config,
+            );
+
+            match journey.validate() {
+                Output::Success(success) => success.traces,
+                Output::Error(err) => panic!("{}", err.cancel_reason),
+                _ => panic!("expected a journey output"),
+            }
+        };
+
+        let recorded = traces(Config::default());
+        let resampled = traces(Config {
+            resample: Some(ResampleStep::Meters(100.0)),
+            ..Default::default()
+        });
+
+        // Only the comparisons use the resampled traces
+        assert_eq!(
+            resampled.passenger_trace.points,
+            recorded.passenger_trace.points
+        );
+        assert_eq!(
+            resampled.passenger_trace.distance,
+            recorded.passenger_trace.distance
+        );
+    }
 }
@@ -0,0 +1,115 @@
+use crate::{config::LatencyConfig, output::LatencyOutput, trace::Trace};
+
+/// Updates within this delay after the creation are part of the upload itself.
+const EDIT_TOLERANCE_MS: i64 = 1_000;
+
+/// Delays between the GPS fix and its creation on the server.
+#[derive(Debug, Clone, Default)]
+pub struct UploadLatency {
+    pub median_ms: Option<i64>,
+    pub p90_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+    /// Share of the points created on the server more than the backfill threshold after the fix.
+    pub backfill_ratio: f64,
+    /// Share of the points updated on the server after their creation.
+    pub edited_ratio: f64,
+    /// Delay between the first fix and the creation of the trace on the server.
+    pub trace_delay_ms: Option<i64>,
+}
+
+fn percentile(sorted: &[i64], percentile: f64) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let idx = ((sorted.len() - 1) as f64 * percentile).round() as usize;
+
+    Some(sorted[idx])
+}
+
+impl<T> Trace<T> {
+    pub fn upload_latency(&self, config: &LatencyConfig) -> UploadLatency {
+        let mut latencies: Vec<i64> = self
+            .points
+            .iter()
+            .filter_map(|p| {
+                p.created_at
+                    .map(|created_at| (created_at - p.timestamp).num_milliseconds())
+            })
+            .collect();
+        latencies.sort_unstable();
+
+        let edited = self
+            .points
+            .iter()
+            .filter_map(|p| Some((p.updated_at? - p.created_at?).num_milliseconds()))
+            .filter(|delay| *delay > EDIT_TOLERANCE_MS)
+            .count();
+
+        let ratio = |count: usize| {
+            if latencies.is_empty() {
+                0.0
+            } else {
+                count as f64 / latencies.len() as f64
+            }
+        };
+
+        let backfilled = latencies
+            .iter()
+            .filter(|latency| **latency > config.backfill_threshold_ms)
+            .count();
+
+        UploadLatency {
+            median_ms: percentile(&latencies, 0.5),
+            p90_ms: percentile(&latencies, 0.9),
+            max_ms: latencies.last().copied(),
+            backfill_ratio: ratio(backfilled),
+            edited_ratio: ratio(edited),
+            trace_delay_ms: self
+                .created_at
+                .zip(self.points.first())
+                .map(|(created_at, first)| (created_at - first.timestamp).num_milliseconds()),
+        }
+    }
+}
+
+impl From<&UploadLatency> for LatencyOutput {
+    fn from(value: &UploadLatency) -> Self {
+        Self {
+            median_ms: value.median_ms,
+            p90_ms: value.p90_ms,
+            max_ms: value.max_ms,
+            backfill_ratio: value.backfill_ratio,
+            edited_ratio: value.edited_ratio,
+            trace_delay_ms: value.trace_delay_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    #[test]
+    fn test_upload_latency() {
+        let trace = TraceBuilder::new("trace_1")
+            .fixes(0..10, |i| (2.3522, 48.8566, Duration::seconds(i * 10)))
+            .with(|i, p| {
+                // The last 2 points were uploaded 3 hours late, the last one edited afterwards
+                let created_at = p.timestamp + Duration::seconds(if i < 8 { 5 } else { 3 * 3600 });
+                p.created_at = Some(created_at);
+                p.updated_at = Some(created_at + Duration::seconds(if i == 9 { 60 } else { 0 }));
+            })
+            .build();
+
+        let latency = trace.upload_latency(&LatencyConfig::default());
+
+        assert_eq!(latency.median_ms, Some(5_000));
+        assert_eq!(latency.max_ms, Some(3 * 3_600_000));
+        assert_eq!(latency.backfill_ratio, 0.2);
+        assert_eq!(latency.edited_ratio, 0.1);
+        assert_eq!(latency.trace_delay_ms, None);
+    }
+}
@@ -1,10 +1,37 @@
+pub mod carpool;
 pub mod cli;
+pub mod common;
+pub mod config;
+pub mod consistency;
+pub mod corridor;
+pub mod detour;
+pub mod duplicate;
 pub mod error;
+pub mod fingerprint;
 pub mod france;
+pub mod gtfs;
+pub mod history;
 pub mod input;
 pub mod journey;
+pub mod latency;
+pub mod map_matching;
+pub mod mode;
+pub mod normalize;
 pub mod output;
+pub mod pickup;
 pub mod point;
+pub mod profile;
+pub mod resample;
+pub mod role;
+pub mod similarity;
+pub mod simplify;
+pub mod skew;
+pub mod spatial;
+pub mod spoofing;
+pub mod stops;
+pub mod store;
+#[cfg(test)]
+mod test_utils;
 pub mod trace;
 pub mod visualize;
 
@@ -1,18 +1,37 @@
 use clap::Parser;
-use fc_journey_validation::{cli::Cli, input::JourneyInput, journey::Journey, output::Output};
+use fc_journey_validation::{
+    carpool::Carpool, cli::Cli, config::Config, input::JourneyInput, journey::Journey,
+    output::Output,
+};
 use std::io::{self, Write};
 
 fn main() {
     let cli = Cli::parse();
 
-    let journey_result = match cli.file_path {
-        Some(path) => JourneyInput::try_from(path),
-        None => JourneyInput::from_stdin(),
-    }
-    .and_then(Journey::try_from);
+    let config = match cli.config_path {
+        Some(path) => Config::try_from(path),
+        None => Ok(Config::default()),
+    };
+
+    let input = config.and_then(|config| {
+        match cli.file_path {
+            Some(path) => JourneyInput::try_from(path),
+            None => JourneyInput::from_stdin(),
+        }
+        .map(|journey| (journey, config))
+    });
 
-    let output = match journey_result {
-        Ok(journey) => journey.validate(),
+    let output = match input {
+        Ok((journey, config)) if journey.passenger_ids.is_some() => {
+            match Carpool::new(journey, config) {
+                Ok(carpool) => carpool.validate(),
+                Err(err) => Output::from(err),
+            }
+        }
+        Ok((journey, config)) => match Journey::new(journey, config) {
+            Ok(journey) => journey.validate(),
+            Err(err) => Output::from(err),
+        },
         Err(err) => Output::from(err),
     };
 
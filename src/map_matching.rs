@@ -0,0 +1,419 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    path::Path,
+};
+
+use geo::{Coord, HaversineDistance, Line, LineString, Point};
+use geojson::{FeatureCollection, GeoJson};
+use rstar::{primitives::GeomWithData, RTree};
+
+use crate::{
+    config::MapMatchingConfig,
+    output::MapMatchOutput,
+    spatial::{segments_within, SegmentIndex},
+    trace::Trace,
+    Result,
+};
+
+const MAX_CANDIDATES: usize = 5;
+const MAX_ROUTE_FACTOR: f64 = 3.0;
+
+struct RoadEdge {
+    from: usize,
+    to: usize,
+    length: f64,
+}
+
+/// Undirected road graph built from the highway ways of an OSM extract.
+pub struct RoadNetwork {
+    nodes: Vec<Coord<f64>>,
+    edges: Vec<RoadEdge>,
+    adjacency: Vec<Vec<usize>>,
+    index: SegmentIndex,
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    edge: usize,
+    fraction: f64,
+    distance: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MapMatch {
+    pub road_distance: f64,
+    pub matched_ratio: f64,
+    pub mean_offset: f64,
+    pub breaks: usize,
+}
+
+#[derive(PartialEq)]
+struct Visit {
+    node: usize,
+    distance: f64,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl RoadNetwork {
+    pub fn from_linestrings<I: IntoIterator<Item = LineString<f64>>>(linestrings: I) -> Self {
+        let mut node_ids: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut nodes = vec![];
+        let mut edges = vec![];
+
+        for linestring in linestrings {
+            let ids: Vec<usize> = linestring
+                .coords()
+                .map(|coord| {
+                    *node_ids
+                        .entry((coord.x.to_bits(), coord.y.to_bits()))
+                        .or_insert_with(|| {
+                            nodes.push(*coord);
+                            nodes.len() - 1
+                        })
+                })
+                .collect();
+
+            for pair in ids.windows(2) {
+                if pair[0] == pair[1] {
+                    continue;
+                }
+
+                edges.push(RoadEdge {
+                    from: pair[0],
+                    to: pair[1],
+                    length: Point::from(nodes[pair[0]])
+                        .haversine_distance(&Point::from(nodes[pair[1]])),
+                });
+            }
+        }
+
+        let mut adjacency = vec![vec![]; nodes.len()];
+
+        for (idx, edge) in edges.iter().enumerate() {
+            adjacency[edge.from].push(idx);
+            adjacency[edge.to].push(idx);
+        }
+
+        let index = RTree::bulk_load(
+            edges
+                .iter()
+                .enumerate()
+                .map(|(idx, edge)| {
+                    GeomWithData::new(Line::new(nodes[edge.from], nodes[edge.to]), idx)
+                })
+                .collect(),
+        );
+
+        Self {
+            nodes,
+            edges,
+            adjacency,
+            index,
+        }
+    }
+
+    /// Loads a GeoJSON export of the road ways, e.g. from a PBF extract with
+    /// `osmium tags-filter extract.osm.pbf w/highway -o roads.osm.pbf` then
+    /// `osmium export roads.osm.pbf -o roads.geojson`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let geojson: GeoJson = std::fs::read_to_string(path)?.parse().map_err(Box::new)?;
+        let collection = FeatureCollection::try_from(geojson).map_err(Box::new)?;
+
+        let linestrings = collection
+            .features
+            .into_iter()
+            .filter_map(|feature| feature.geometry)
+            .flat_map(
+                |geometry| match geo::Geometry::<f64>::try_from(geometry.value) {
+                    Ok(geo::Geometry::LineString(linestring)) => vec![linestring],
+                    Ok(geo::Geometry::MultiLineString(linestrings)) => linestrings.0,
+                    _ => vec![],
+                },
+            );
+
+        Ok(Self::from_linestrings(linestrings))
+    }
+
+    fn candidates(&self, point: Point<f64>, radius: f64) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = segments_within(&self.index, point, radius)
+            .map(|(segment, projected, distance)| {
+                let edge = &self.edges[segment.data];
+                let fraction = if edge.length > 0.0 {
+                    (Point::from(self.nodes[edge.from]).haversine_distance(&projected)
+                        / edge.length)
+                        .clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+
+                Candidate {
+                    edge: segment.data,
+                    fraction,
+                    distance,
+                }
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+
+    /// Shortest network distances from a candidate to every node reachable within `limit`.
+    fn reachable_from(&self, candidate: &Candidate, limit: f64) -> HashMap<usize, f64> {
+        let edge = &self.edges[candidate.edge];
+        let mut distances: HashMap<usize, f64> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        for (node, distance) in [
+            (edge.from, candidate.fraction * edge.length),
+            (edge.to, (1.0 - candidate.fraction) * edge.length),
+        ] {
+            heap.push(Visit { node, distance });
+        }
+
+        while let Some(Visit { node, distance }) = heap.pop() {
+            if distance > limit || distances.contains_key(&node) {
+                continue;
+            }
+
+            distances.insert(node, distance);
+
+            for &idx in &self.adjacency[node] {
+                let edge = &self.edges[idx];
+                let next = if edge.from == node {
+                    edge.to
+                } else {
+                    edge.from
+                };
+
+                if !distances.contains_key(&next) {
+                    heap.push(Visit {
+                        node: next,
+                        distance: distance + edge.length,
+                    });
+                }
+            }
+        }
+
+        distances
+    }
+
+    fn route_distance(
+        &self,
+        from: &Candidate,
+        reachable: &HashMap<usize, f64>,
+        to: &Candidate,
+    ) -> Option<f64> {
+        let edge = &self.edges[to.edge];
+
+        if from.edge == to.edge {
+            return Some((to.fraction - from.fraction).abs() * edge.length);
+        }
+
+        let via_from = reachable
+            .get(&edge.from)
+            .map(|d| d + to.fraction * edge.length);
+        let via_to = reachable
+            .get(&edge.to)
+            .map(|d| d + (1.0 - to.fraction) * edge.length);
+
+        match (via_from, via_to) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Hidden Markov model matching (Newson & Krumm): gaussian emission on the distance to
+    /// the road, exponential transition on the gap between route and great circle distances.
+    pub fn match_trace<T>(&self, trace: &Trace<T>, config: &MapMatchingConfig) -> MapMatch {
+        let steps: Vec<(Point<f64>, Vec<Candidate>)> = trace
+            .points
+            .iter()
+            .map(Point::from)
+            .map(|point| (point, self.candidates(point, config.search_radius)))
+            .filter(|(_, candidates)| !candidates.is_empty())
+            .collect();
+
+        let emission =
+            |candidate: &Candidate| -0.5 * (candidate.distance / config.gps_sigma).powi(2);
+
+        let mut chains: Vec<Vec<(Candidate, f64)>> = vec![];
+        let mut scores: Vec<f64> = vec![];
+        let mut history: Vec<Vec<(usize, f64)>> = vec![];
+        let mut chain_steps: Vec<&Vec<Candidate>> = vec![];
+
+        for (idx, (point, candidates)) in steps.iter().enumerate() {
+            if idx == 0 {
+                scores = candidates.iter().map(emission).collect();
+                history = vec![vec![(0, 0.0); candidates.len()]];
+                chain_steps = vec![candidates];
+                continue;
+            }
+
+            let (prev_point, prev_candidates) = &steps[idx - 1];
+            let great_circle = prev_point.haversine_distance(point);
+            let limit = great_circle * MAX_ROUTE_FACTOR + 2.0 * config.search_radius;
+
+            let mut next_scores = vec![f64::NEG_INFINITY; candidates.len()];
+            let mut next_history = vec![(0, 0.0); candidates.len()];
+
+            for (i, from) in prev_candidates.iter().enumerate() {
+                if scores[i] == f64::NEG_INFINITY {
+                    continue;
+                }
+
+                let reachable = self.reachable_from(from, limit);
+
+                for (j, to) in candidates.iter().enumerate() {
+                    let Some(route) = self.route_distance(from, &reachable, to) else {
+                        continue;
+                    };
+
+                    let score =
+                        scores[i] - (route - great_circle).abs() / config.beta + emission(to);
+
+                    if score > next_scores[j] {
+                        next_scores[j] = score;
+                        next_history[j] = (i, route);
+                    }
+                }
+            }
+
+            if next_scores.iter().all(|s| *s == f64::NEG_INFINITY) {
+                chains.push(backtrack(&chain_steps, &history, &scores));
+                scores = candidates.iter().map(emission).collect();
+                history = vec![vec![(0, 0.0); candidates.len()]];
+                chain_steps = vec![candidates];
+                continue;
+            }
+
+            scores = next_scores;
+            history.push(next_history);
+            chain_steps.push(candidates);
+        }
+
+        chains.push(backtrack(&chain_steps, &history, &scores));
+        chains.retain(|chain| !chain.is_empty());
+
+        let matched: Vec<&(Candidate, f64)> = chains.iter().flatten().collect();
+        let road_distance = matched.iter().map(|(_, distance)| distance).sum();
+        let mean_offset = if matched.is_empty() {
+            0.0
+        } else {
+            matched.iter().map(|(c, _)| c.distance).sum::<f64>() / matched.len() as f64
+        };
+
+        MapMatch {
+            road_distance,
+            matched_ratio: matched.len() as f64 / trace.points.len().max(1) as f64,
+            mean_offset,
+            breaks: chains.len().saturating_sub(1),
+        }
+    }
+}
+
+fn backtrack(
+    chain_steps: &[&Vec<Candidate>],
+    history: &[Vec<(usize, f64)>],
+    scores: &[f64],
+) -> Vec<(Candidate, f64)> {
+    let Some((mut best, _)) = scores
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    else {
+        return vec![];
+    };
+
+    let mut chain = Vec::with_capacity(chain_steps.len());
+
+    for step in (0..chain_steps.len()).rev() {
+        let (prev, distance) = history[step][best];
+        chain.push((chain_steps[step][best].clone(), distance));
+        best = prev;
+    }
+
+    chain.reverse();
+    chain
+}
+
+impl From<&MapMatch> for MapMatchOutput {
+    fn from(value: &MapMatch) -> Self {
+        Self {
+            road_distance: value.road_distance,
+            matched_ratio: value.matched_ratio,
+            mean_offset: value.mean_offset,
+            breaks: value.breaks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    fn create_network() -> RoadNetwork {
+        RoadNetwork::from_linestrings([
+            LineString::from(vec![(2.35, 48.85), (2.36, 48.85), (2.37, 48.85)]),
+            LineString::from(vec![(2.36, 48.85), (2.36, 48.86)]),
+        ])
+    }
+
+    fn create_trace(coords: &[(f64, f64)]) -> Trace {
+        TraceBuilder::new("trace_1")
+            .fixes(0..coords.len() as i64, |i| {
+                let (x, y) = coords[i as usize];
+                (x, y, Duration::seconds(i * 10))
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_match_trace_along_road() {
+        let network = create_network();
+        let trace = create_trace(&[
+            (2.351, 48.8501),
+            (2.355, 48.8499),
+            (2.359, 48.8501),
+            (2.3601, 48.853),
+            (2.3599, 48.857),
+        ]);
+
+        let matched = network.match_trace(&trace, &MapMatchingConfig::default());
+
+        let expected = Point::new(2.351, 48.85).haversine_distance(&Point::new(2.36, 48.85))
+            + Point::new(2.36, 48.85).haversine_distance(&Point::new(2.36, 48.857));
+
+        assert_eq!(matched.matched_ratio, 1.0);
+        assert_eq!(matched.breaks, 0);
+        assert!((matched.road_distance - expected).abs() < 5.0);
+        assert!(matched.mean_offset < 15.0);
+    }
+
+    #[test]
+    fn test_match_trace_off_road() {
+        let network = create_network();
+        let trace = create_trace(&[(2.351, 48.8501), (2.355, 48.855), (2.359, 48.8501)]);
+
+        let matched = network.match_trace(&trace, &MapMatchingConfig::default());
+
+        assert!((matched.matched_ratio - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+}
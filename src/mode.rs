@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use geo::{HaversineDistance, Point};
+use serde::Serialize;
+
+use crate::trace::Trace;
+
+const SMOOTHING_WINDOW: usize = 2;
+const MAX_STATIONARY_SPEED: f64 = 0.5;
+const MAX_WALK_SPEED: f64 = 2.5;
+const MAX_BIKE_SPEED: f64 = 7.0;
+const MIN_TRAIN_SPEED: f64 = 42.0;
+const MIN_CAR_ACCELERATION: f64 = 1.5;
+/// Slow runs shorter than this between two runs of the same motorized mode are traffic stops.
+const MAX_STOP_RUN_MS: i64 = 90_000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum TransportMode {
+    Stationary,
+    Walk,
+    Bike,
+    Car,
+    Train,
+}
+
+impl TransportMode {
+    fn is_motorized(&self) -> bool {
+        matches!(self, TransportMode::Car | TransportMode::Train)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModeSegment {
+    pub mode: TransportMode,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub distance: f64,
+}
+
+impl ModeSegment {
+    fn duration_ms(&self) -> i64 {
+        self.end_time
+            .signed_duration_since(self.start_time)
+            .num_milliseconds()
+    }
+}
+
+fn classify(speed: f64, acceleration: f64) -> TransportMode {
+    match speed {
+        s if s < MAX_STATIONARY_SPEED => TransportMode::Stationary,
+        s if s < MAX_WALK_SPEED => TransportMode::Walk,
+        s if s < MAX_BIKE_SPEED && acceleration < MIN_CAR_ACCELERATION => TransportMode::Bike,
+        s if s >= MIN_TRAIN_SPEED => TransportMode::Train,
+        _ => TransportMode::Car,
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+impl<T> Trace<T> {
+    /// Labels every segment between consecutive points with a transport mode, using the smoothed
+    /// speed (reported by the device when available), the local acceleration and short stops.
+    pub fn mode_segments(&self) -> Vec<ModeSegment> {
+        let segments: Vec<(f64, f64, f64)> = self
+            .points
+            .windows(2)
+            .map(|w| {
+                let distance = Point::from(&w[0]).haversine_distance(&Point::from(&w[1]));
+                let seconds = w[1].get_ms_delta_with(&w[0]) as f64 / 1000.0;
+                let speed = match w[1].speed {
+                    Some(speed) if speed >= 0.0 => speed,
+                    _ if seconds > 0.0 => distance / seconds,
+                    _ => 0.0,
+                };
+
+                (distance, seconds, speed)
+            })
+            .collect();
+
+        let speeds: Vec<f64> = (0..segments.len())
+            .map(|i| {
+                let window = i.saturating_sub(SMOOTHING_WINDOW)
+                    ..(i + SMOOTHING_WINDOW + 1).min(segments.len());
+                median(&mut segments[window].iter().map(|s| s.2).collect::<Vec<f64>>())
+            })
+            .collect();
+
+        let mut modes: Vec<ModeSegment> = self
+            .points
+            .windows(2)
+            .enumerate()
+            .map(|(i, w)| {
+                let window = i.saturating_sub(SMOOTHING_WINDOW)
+                    ..(i + SMOOTHING_WINDOW + 1).min(segments.len());
+                let acceleration = window
+                    .clone()
+                    .skip(1)
+                    .filter(|&j| segments[j].1 > 0.0)
+                    .map(|j| (segments[j].2 - segments[j - 1].2).abs() / segments[j].1)
+                    .fold(0.0, f64::max);
+
+                ModeSegment {
+                    mode: classify(speeds[i], acceleration),
+                    start_time: w[0].timestamp,
+                    end_time: w[1].timestamp,
+                    distance: segments[i].0,
+                }
+            })
+            .collect();
+
+        relabel_stops(&mut modes);
+
+        modes
+    }
+
+    /// Distance travelled in each mode between `start` and `end`, segments crossing a bound
+    /// count for the share of their duration inside the window.
+    pub fn mode_distances(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BTreeMap<TransportMode, f64> {
+        self.mode_segments()
+            .into_iter()
+            .filter(|s| s.end_time > start && s.start_time < end)
+            .fold(BTreeMap::new(), |mut acc, s| {
+                let duration = s.duration_ms();
+                let inside = s
+                    .end_time
+                    .min(end)
+                    .signed_duration_since(s.start_time.max(start))
+                    .num_milliseconds();
+                let ratio = if duration > 0 {
+                    inside as f64 / duration as f64
+                } else {
+                    1.0
+                };
+
+                *acc.entry(s.mode).or_insert(0.0) += s.distance * ratio;
+                acc
+            })
+    }
+}
+
+fn relabel_stops(modes: &mut [ModeSegment]) {
+    let mut runs: Vec<(usize, usize)> = vec![];
+
+    for (idx, segment) in modes.iter().enumerate() {
+        match runs.last_mut() {
+            Some((start, end)) if modes[*start].mode == segment.mode => *end = idx,
+            _ => runs.push((idx, idx)),
+        }
+    }
+
+    for window in runs.windows(3) {
+        let (before, (start, end), after) = (window[0], window[1], window[2]);
+        let mode = modes[before.0].mode;
+
+        if !mode.is_motorized() || modes[after.0].mode != mode || modes[start].mode.is_motorized() {
+            continue;
+        }
+
+        let duration: i64 = modes[start..=end]
+            .iter()
+            .map(ModeSegment::duration_ms)
+            .sum();
+
+        if duration <= MAX_STOP_RUN_MS {
+            modes[start..=end].iter_mut().for_each(|s| s.mode = mode);
+        }
+    }
+}
+
+/// Share of the moving distance travelled by car, `None` when nothing moved.
+pub fn car_ratio(distances: &BTreeMap<TransportMode, f64>) -> Option<f64> {
+    let moving = distances
+        .iter()
+        .filter(|(mode, _)| **mode != TransportMode::Stationary)
+        .fold(0.0, |acc, (_, distance)| acc + distance);
+
+    (moving > 0.0).then(|| distances.get(&TransportMode::Car).unwrap_or(&0.0) / moving)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    fn create_trace(speeds: &[f64]) -> Trace {
+        // 10 seconds per point, ~73 meters per 0.001 degree of longitude at this latitude
+        let offsets: Vec<f64> = std::iter::once(0.0)
+            .chain(speeds.iter().copied())
+            .scan(0.0, |dx, speed| {
+                *dx += speed * 10.0 / 73_000.0;
+                Some(*dx)
+            })
+            .collect();
+
+        TraceBuilder::new("trace_1")
+            .fixes(0..offsets.len() as i64, |i| {
+                (
+                    2.3522 + offsets[i as usize],
+                    48.8566,
+                    Duration::seconds(i * 10),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_car_with_traffic_light() {
+        let mut speeds = vec![14.0; 10];
+        speeds.extend([0.0; 4]);
+        speeds.extend([14.0; 10]);
+        let trace = create_trace(&speeds);
+
+        let modes = trace.mode_segments();
+
+        assert!(modes.iter().all(|s| s.mode == TransportMode::Car));
+    }
+
+    #[test]
+    fn test_walk_and_train() {
+        let mut speeds = vec![1.4; 10];
+        speeds.extend([60.0; 10]);
+        let trace = create_trace(&speeds);
+        let (start, end) = trace.get_edges();
+
+        let distances = trace.mode_distances(start.timestamp, end.timestamp);
+
+        assert!(distances[&TransportMode::Walk] > 0.0);
+        assert!(distances[&TransportMode::Train] > distances[&TransportMode::Walk]);
+        assert_eq!(car_ratio(&distances), Some(0.0));
+    }
+
+    #[test]
+    fn test_window_between_fixes() {
+        let trace = create_trace(&[14.0; 10]);
+        let start = trace.points[3].timestamp + Duration::seconds(2);
+
+        let inside = trace.mode_distances(start, start + Duration::seconds(5));
+        let before = trace.mode_distances(start - Duration::hours(1), start - Duration::minutes(1));
+
+        assert!((inside[&TransportMode::Car] - 70.0).abs() < 2.0);
+        assert_eq!(car_ratio(&inside), Some(1.0));
+        assert_eq!(car_ratio(&before), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use crate::{
+    input::TraceInput,
+    output::{NormalizationOutput, TraceNormalizationOutput},
+    point::PointWithId,
+    trace::Trace,
+};
+
+/// Corrections made to the points of a trace before the validation, by point id.
+#[derive(Debug, Clone, Default)]
+pub struct Normalization {
+    /// Points recorded before the point preceding them in the input.
+    pub unordered_points: Vec<String>,
+    pub duplicate_ids: Vec<String>,
+    /// Points with the same timestamp and coordinates as the previous point.
+    pub identical_fixes: Vec<String>,
+    /// Points whose `gps_trace_id` is not the id of the trace holding them.
+    pub foreign_points: Vec<String>,
+    /// Points outside France whose latitude and longitude were swapped.
+    pub swapped_coordinates: Vec<String>,
+}
+
+fn is_identical_fix(a: &PointWithId, b: &PointWithId) -> bool {
+    a.timestamp == b.timestamp && a.x == b.x && a.y == b.y
+}
+
+impl Trace {
+    /// Merges the fragments of a user into a trace sorted by timestamp, without duplicate nor
+    /// foreign points.
+    pub fn normalized(fragments: &[&TraceInput]) -> (Self, Normalization) {
+        let mut normalization = Normalization::default();
+
+        let fragments: Vec<TraceInput> = fragments
+            .iter()
+            .map(|fragment| {
+                let mut fragment = (*fragment).clone();
+                let (owned, foreign) = fragment
+                    .points
+                    .into_iter()
+                    .partition(|p| p.gps_trace_id == fragment.id);
+
+                fragment.points = owned;
+                normalization
+                    .foreign_points
+                    .extend(foreign.into_iter().map(|p| p.id));
+                fragment
+            })
+            .collect();
+
+        let mut trace = Trace::from_fragments(&fragments.iter().collect::<Vec<_>>());
+
+        let mut ids = HashSet::new();
+        trace.points.retain(|p| {
+            let unique = ids.insert(p.id.clone());
+
+            if !unique {
+                normalization.duplicate_ids.push(p.id.clone());
+            }
+
+            unique
+        });
+
+        normalization.unordered_points = trace
+            .points
+            .windows(2)
+            .filter(|w| w[1].timestamp < w[0].timestamp)
+            .map(|w| w[1].id.clone())
+            .collect();
+        trace.points.sort_by_key(|p| p.timestamp);
+
+        let mut points: Vec<PointWithId> = Vec::with_capacity(trace.points.len());
+
+        for point in trace.points {
+            match points.last() {
+                Some(prev) if is_identical_fix(prev, &point) => {
+                    normalization.identical_fixes.push(point.id);
+                }
+                _ => points.push(point),
+            }
+        }
+
+        for point in points.iter_mut().filter(|p| !p.is_in_france()) {
+            let swapped = PointWithId {
+                x: point.y,
+                y: point.x,
+                ..point.clone()
+            };
+
+            if swapped.is_in_france() {
+                normalization.swapped_coordinates.push(point.id.clone());
+                *point = swapped;
+            }
+        }
+
+        trace.points = points;
+
+        (trace, normalization)
+    }
+}
+
+impl From<&Normalization> for TraceNormalizationOutput {
+    fn from(value: &Normalization) -> Self {
+        Self {
+            unordered_points: value.unordered_points.clone(),
+            duplicate_ids: value.duplicate_ids.clone(),
+            identical_fixes: value.identical_fixes.clone(),
+            foreign_points: value.foreign_points.clone(),
+            swapped_coordinates: value.swapped_coordinates.clone(),
+        }
+    }
+}
+
+impl NormalizationOutput {
+    pub fn new(driver: &Normalization, passenger: &Normalization) -> Self {
+        Self {
+            driver_trace: driver.into(),
+            passenger_trace: passenger.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized() {
+        let point = |id: &str, gps_trace_id: &str, seconds: u32, lat: f64, lon: f64| {
+            serde_json::json!({
+                "id": id,
+                "createdAt": "2024-06-18T12:00:00Z",
+                "updatedAt": "2024-06-18T12:00:00Z",
+                "latitude": lat,
+                "longitude": lon,
+                "timestamp": format!("2024-06-18T12:00:{seconds:02}Z"),
+                "gpsTraceId": gps_trace_id,
+            })
+        };
+        let fragment: TraceInput = serde_json::from_value(serde_json::json!({
+            "id": "trace_1",
+            "createdAt": "2024-06-18T12:00:00Z",
+            "updatedAt": "2024-06-18T12:00:00Z",
+            "userId": "driver",
+            "points": [
+                point("1", "trace_1", 0, 48.8566, 2.3522),
+                point("3", "trace_1", 20, 48.8586, 2.3542),
+                point("2", "trace_1", 10, 2.3532, 48.8576),
+                point("2", "trace_1", 10, 2.3532, 48.8576),
+                point("4", "trace_1", 20, 48.8586, 2.3542),
+                point("5", "trace_2", 30, 48.8596, 2.3552),
+            ],
+        }))
+        .unwrap();
+
+        let (trace, normalization) = Trace::normalized(&[&fragment]);
+
+        assert_eq!(
+            trace
+                .points
+                .iter()
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1", "2", "3"]
+        );
+        assert_eq!(trace.points[1].y, 48.8576);
+        assert_eq!(normalization.foreign_points, ["5"]);
+        assert_eq!(normalization.duplicate_ids, ["2"]);
+        assert_eq!(normalization.unordered_points, ["2"]);
+        assert_eq!(normalization.identical_fixes, ["4"]);
+        assert_eq!(normalization.swapped_coordinates, ["2"]);
+    }
+}
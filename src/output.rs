@@ -1,14 +1,17 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::error::JourneyValidationError;
+use crate::{error::JourneyValidationError, mode::TransportMode};
 
 #[skip_serializing_none]
 #[derive(Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct OutputError {
     pub cancel_reason: String,
+    pub details: Option<serde_json::Value>,
 }
 
 #[skip_serializing_none]
@@ -18,7 +21,25 @@ pub struct OutputSuccess {
     pub common_distance: f64,
     pub common_start_point: PointOutput,
     pub common_end_point: PointOutput,
+    pub pickup_point: Option<PointOutput>,
+    pub drop_off_point: Option<PointOutput>,
+    pub detour: Option<DetourOutput>,
     pub average_confidence: f64,
+    pub confidence_profile: Vec<ConfidenceSegmentOutput>,
+    pub clock_skew: Option<ClockSkewOutput>,
+    pub map_matching: Option<MapMatchingOutput>,
+    pub stops: StopsOutput,
+    pub transport_modes: TransportModesOutput,
+    pub spoofing: SpoofingAnalysisOutput,
+    pub consistency: ConsistenciesOutput,
+    pub upload_latency: LatenciesOutput,
+    pub corridor: CorridorsOutput,
+    pub similarity: Option<SimilarityOutput>,
+    pub fragments: FragmentsOutput,
+    pub normalization: NormalizationOutput,
+    pub duplicate: DuplicateOutput,
+    pub role_swap: RoleSwapOutput,
+    pub warnings: Vec<String>,
     pub traces: TracesOutput,
 }
 
@@ -29,6 +50,7 @@ pub struct OutputSuccess {
 pub enum Output {
     Error(OutputError),
     Success(OutputSuccess),
+    Carpool(CarpoolOutput),
     #[default]
     Empty,
 }
@@ -55,10 +77,240 @@ pub struct PointOutput {
     pub longitude: f64,
 }
 
-impl From<JourneyValidationError> for Output {
-    fn from(value: JourneyValidationError) -> Self {
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkewOutput {
+    pub offset_ms: i64,
+    pub mean_distance: f64,
+    pub implausible: bool,
+    pub applied: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapMatchingOutput {
+    pub driver_trace: MapMatchOutput,
+    pub passenger_trace: MapMatchOutput,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapMatchOutput {
+    pub road_distance: f64,
+    pub matched_ratio: f64,
+    pub mean_offset: f64,
+    pub breaks: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopsOutput {
+    pub driver_trace: TraceStopsOutput,
+    pub passenger_trace: TraceStopsOutput,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceStopsOutput {
+    pub stationary_ratio: f64,
+    pub stops: Vec<StopOutput>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopOutput {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub points_count: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportModesOutput {
+    pub common_car_ratio: Option<f64>,
+    pub driver_trace: BTreeMap<TransportMode, f64>,
+    pub passenger_trace: BTreeMap<TransportMode, f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpoofingAnalysisOutput {
+    pub driver_trace: SpoofingOutput,
+    pub passenger_trace: SpoofingOutput,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpoofingOutput {
+    pub score: Option<f64>,
+    pub regular_intervals: Option<f64>,
+    pub constant_accuracy: Option<f64>,
+    pub zero_altitude: Option<f64>,
+    pub identical_speeds: Option<f64>,
+    pub grid_coordinates: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistenciesOutput {
+    pub driver_trace: ConsistencyOutput,
+    pub passenger_trace: ConsistencyOutput,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyOutput {
+    pub speed_ratio: Option<f64>,
+    pub heading_ratio: Option<f64>,
+    pub speed_samples: usize,
+    pub heading_samples: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatenciesOutput {
+    pub driver_trace: LatencyOutput,
+    pub passenger_trace: LatencyOutput,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyOutput {
+    pub median_ms: Option<i64>,
+    pub p90_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+    pub backfill_ratio: f64,
+    pub edited_ratio: f64,
+    pub trace_delay_ms: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FragmentsOutput {
+    pub driver_trace: usize,
+    pub passenger_trace: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizationOutput {
+    pub driver_trace: TraceNormalizationOutput,
+    pub passenger_trace: TraceNormalizationOutput,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceNormalizationOutput {
+    pub unordered_points: Vec<String>,
+    pub duplicate_ids: Vec<String>,
+    pub identical_fixes: Vec<String>,
+    pub foreign_points: Vec<String>,
+    pub swapped_coordinates: Vec<String>,
+}
+
+/// Each trace inside the corridor around the other one.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorridorsOutput {
+    pub driver_trace: CorridorOutput,
+    pub passenger_trace: CorridorOutput,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorridorOutput {
+    pub inside_distance: f64,
+    pub distance: f64,
+    pub ratio: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarityOutput {
+    pub hausdorff_distance: f64,
+    pub dtw_distance: f64,
+    pub lcss_ratio: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidenceSegmentOutput {
+    pub start_distance: f64,
+    pub end_distance: f64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub confidence: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateOutput {
+    pub identical_coordinates: f64,
+    pub shifted_coordinates: f64,
+    pub identical_timestamps: f64,
+    pub shifted_timestamps: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleSwapOutput {
+    pub driver_lead_distance: f64,
+    pub driver_trail_distance: f64,
+    pub passenger_lead_distance: f64,
+    pub passenger_trail_distance: f64,
+    pub suspected: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetourOutput {
+    pub total_distance: f64,
+    pub shared_distance: f64,
+    pub direct_distance: f64,
+    pub detour_distance: f64,
+    pub detour_ratio: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CarpoolOutput {
+    pub verdict: Verdict,
+    pub passengers: Vec<PassengerOutput>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PassengerOutput {
+    pub passenger_id: String,
+    #[serde(flatten)]
+    pub output: Output,
+}
+
+/// Valid when every passenger is validated, partial when only some of them are.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    Valid,
+    Partial,
+    Invalid,
+}
+
+impl From<&JourneyValidationError> for Output {
+    fn from(value: &JourneyValidationError) -> Self {
         Self::Error(OutputError {
             cancel_reason: value.to_string(),
+            details: value.details(),
         })
     }
 }
+
+impl From<JourneyValidationError> for Output {
+    fn from(value: JourneyValidationError) -> Self {
+        Self::from(&value)
+    }
+}
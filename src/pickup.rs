@@ -0,0 +1,71 @@
+use geo::{HaversineDistance, Point};
+
+use crate::{config::PickupConfig, point::PointWithId, trace::Trace};
+
+/// Where the passenger trace joins and leaves the driver's trajectory.
+#[derive(Debug, Clone)]
+pub struct PickupDropOff<'a> {
+    pub pickup: &'a PointWithId,
+    pub drop_off: &'a PointWithId,
+}
+
+impl<T> Trace<T> {
+    /// Whether each point is within the radius of the other trace position at the same time.
+    pub(crate) fn together_with<U>(&self, other: &Trace<U>, radius: f64) -> Vec<bool> {
+        self.points
+            .iter()
+            .map(|p| {
+                other
+                    .position_at(p.timestamp)
+                    .is_some_and(|position| position.haversine_distance(&Point::from(p)) <= radius)
+            })
+            .collect()
+    }
+
+    /// First and last passenger points starting and ending a run of `min_points` points spent
+    /// next to the driver, so a single crossing of both trajectories is not a pickup.
+    pub fn pickup_drop_off<U>(
+        &self,
+        driver: &Trace<U>,
+        config: &PickupConfig,
+    ) -> Option<PickupDropOff<'_>> {
+        let together = self.together_with(driver, config.radius);
+        let min_points = config.min_points.max(1);
+
+        let pickup_idx = together
+            .windows(min_points)
+            .position(|w| w.iter().all(|t| *t))?;
+        let drop_off_idx = together
+            .windows(min_points)
+            .rposition(|w| w.iter().all(|t| *t))?
+            + min_points
+            - 1;
+
+        Some(PickupDropOff {
+            pickup: &self.points[pickup_idx],
+            drop_off: &self.points[drop_off_idx],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::eastward_trace;
+
+    #[test]
+    fn test_pickup_drop_off() {
+        let driver = eastward_trace("driver", 0, 20, 0.0);
+        let mut passenger = eastward_trace("passenger", 3, 17, 0.0);
+        // The passenger walks towards the pickup point and away from the drop-off point
+        passenger.points[..6].iter_mut().for_each(|p| p.y += 0.01);
+        passenger.points[84..].iter_mut().for_each(|p| p.y -= 0.01);
+
+        let PickupDropOff { pickup, drop_off } = passenger
+            .pickup_drop_off(&driver, &PickupConfig::default())
+            .unwrap();
+
+        assert_eq!(pickup.id, "passenger_24");
+        assert_eq!(drop_off.id, "passenger_101");
+    }
+}
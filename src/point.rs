@@ -1,16 +1,37 @@
-use chrono::{DateTime, Utc};
-use geo::{Coord, Point, Within};
+use chrono::{DateTime, Duration, Utc};
+use geo::{Coord, HaversineIntermediate, Point, Within};
 use std::f64;
 
 use crate::{france::FRANCE, input::PointInput, output::PointOutput};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PointWithId {
     pub id: String,
     pub x: f64,
     pub y: f64,
     pub trace_id: String,
     pub timestamp: DateTime<Utc>,
+    pub source: PointSource,
+    /// Speed reported by the device, in meters per second.
+    pub speed: Option<f64>,
+    /// Heading reported by the device, in degrees clockwise from true north.
+    pub heading: Option<f32>,
+    pub accuracy: Option<f64>,
+    pub altitude: Option<f64>,
+    /// Server side creation and update times, missing for synthetic points.
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PointSource {
+    #[default]
+    Recorded,
+    /// Synthetic point created by resampling between two recorded points.
+    Interpolated {
+        previous_id: String,
+        next_id: String,
+    },
 }
 
 impl PointWithId {
@@ -24,6 +45,39 @@ impl PointWithId {
             .num_milliseconds()
             .abs()
     }
+
+    /// Point at `ratio` of the great circle towards `next`, with a linearly interpolated timestamp.
+    pub fn interpolate(&self, next: &PointWithId, ratio: f64) -> PointWithId {
+        // The great circle is undefined between two identical fixes
+        let point = if (self.x, self.y) == (next.x, next.y) {
+            Point::from(self)
+        } else {
+            Point::from(self).haversine_intermediate(&Point::from(next), ratio)
+        };
+        let span = next
+            .timestamp
+            .signed_duration_since(self.timestamp)
+            .num_milliseconds();
+        let timestamp =
+            self.timestamp + Duration::milliseconds((span as f64 * ratio).round() as i64);
+
+        PointWithId {
+            id: format!("{}~{}@{}", self.id, next.id, timestamp.timestamp_millis()),
+            x: point.x(),
+            y: point.y(),
+            trace_id: self.trace_id.clone(),
+            timestamp,
+            source: PointSource::Interpolated {
+                previous_id: self.id.clone(),
+                next_id: next.id.clone(),
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn is_recorded(&self) -> bool {
+        self.source == PointSource::Recorded
+    }
 }
 
 impl From<&PointInput> for PointWithId {
@@ -34,6 +88,13 @@ impl From<&PointInput> for PointWithId {
             timestamp: value.timestamp,
             x: value.longitude,
             y: value.latitude,
+            source: PointSource::Recorded,
+            speed: value.speed,
+            heading: value.heading,
+            accuracy: value.accuracy,
+            altitude: value.altitude,
+            created_at: Some(value.created_at),
+            updated_at: Some(value.updated_at),
         }
     }
 }
@@ -77,6 +138,7 @@ mod tests {
             y: 48.8566,
             trace_id: "trace_1".to_string(),
             timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap(),
+            ..Default::default()
         };
 
         assert!(point_in_france.is_in_france());
@@ -87,6 +149,7 @@ mod tests {
             y: 0.0,
             trace_id: "trace_1".to_string(),
             timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap(),
+            ..Default::default()
         };
 
         assert!(!point_not_in_france.is_in_france());
@@ -100,6 +163,7 @@ mod tests {
             y: 48.8566,
             trace_id: "trace_1".to_string(),
             timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap(),
+            ..Default::default()
         };
 
         let point2 = PointWithId {
@@ -108,6 +172,7 @@ mod tests {
             y: 48.8566,
             trace_id: "trace_2".to_string(),
             timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 1).unwrap(),
+            ..Default::default()
         };
 
         assert_eq!(point1.get_ms_delta_with(&point2), 1000);
@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use geo::{HaversineDistance, LineString, Point};
+
+use crate::{
+    config::ConfidenceProfileConfig,
+    output::ConfidenceSegmentOutput,
+    point::PointWithId,
+    spatial::{frechet_distance, to_local_meters},
+    trace::Trace,
+};
+
+/// Confidence between both traces over a slice of the driver route.
+#[derive(Debug, Clone)]
+pub struct ConfidenceSegment {
+    pub start_distance: f64,
+    pub end_distance: f64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// Zero when the passenger trace does not cover the segment.
+    pub confidence: f64,
+}
+
+/// Confidence in `[0, 1]` from the Fréchet distance in meters between both pieces of route,
+/// null from `max_distance`.
+fn segment_confidence(
+    driver: &LineString<f64>,
+    passenger: &LineString<f64>,
+    max_distance: f64,
+) -> f64 {
+    let Some(origin) = driver.points().next() else {
+        return 0.0;
+    };
+    let project = |linestring: &LineString<f64>| -> LineString<f64> {
+        linestring
+            .coords()
+            .map(|c| to_local_meters(origin, *c))
+            .collect()
+    };
+
+    1.0 - (frechet_distance(&project(driver), &project(passenger)) / max_distance).clamp(0.0, 1.0)
+}
+
+/// Share of the route distance covered by segments reaching `min_confidence`.
+pub fn confident_share(profile: &[ConfidenceSegment], min_confidence: f64) -> f64 {
+    let (confident, total) = profile.iter().fold((0.0, 0.0), |(confident, total), s| {
+        let length = s.end_distance - s.start_distance;
+
+        if s.confidence >= min_confidence {
+            (confident + length, total + length)
+        } else {
+            (confident, total + length)
+        }
+    });
+
+    if total > 0.0 {
+        confident / total
+    } else {
+        0.0
+    }
+}
+
+impl<T> Trace<T> {
+    /// Passenger positions between both timestamps, interpolated at the bounds.
+    fn linestring_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> LineString<f64> {
+        self.position_at(start)
+            .into_iter()
+            .chain(
+                self.points
+                    .iter()
+                    .filter(|p| p.timestamp > start && p.timestamp < end)
+                    .map(Point::from),
+            )
+            .chain(self.position_at(end))
+            .collect::<Vec<Point<f64>>>()
+            .into()
+    }
+
+    /// Cuts the driver route between both timestamps every `config.segment_length` meters and
+    /// compares each piece with the passenger positions over the same period.
+    pub fn confidence_profile<U>(
+        &self,
+        passenger: &Trace<U>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        config: &ConfidenceProfileConfig,
+    ) -> Vec<ConfidenceSegment> {
+        let points: Vec<&PointWithId> = self
+            .points
+            .iter()
+            .filter(|p| p.timestamp >= start && p.timestamp <= end)
+            .collect();
+
+        let mut profile = vec![];
+        let mut segment: Vec<&PointWithId> = vec![];
+        let (mut distance, mut segment_start) = (0.0, 0.0);
+
+        for (idx, point) in points.iter().enumerate() {
+            if let Some(prev) = segment.last() {
+                distance += Point::from(*prev).haversine_distance(&Point::from(*point));
+            }
+
+            segment.push(point);
+
+            let is_last = idx == points.len() - 1;
+
+            if segment.len() < 2 || (distance - segment_start < config.segment_length && !is_last) {
+                continue;
+            }
+
+            let (first, last) = (segment[0], segment[segment.len() - 1]);
+            let driver: LineString<f64> = segment.iter().map(|p| Point::from(*p)).collect();
+            let passenger = passenger.linestring_between(first.timestamp, last.timestamp);
+
+            profile.push(ConfidenceSegment {
+                start_distance: segment_start,
+                end_distance: distance,
+                start_time: first.timestamp,
+                end_time: last.timestamp,
+                confidence: if passenger.0.len() < 2 {
+                    0.0
+                } else {
+                    segment_confidence(&driver, &passenger, config.max_distance)
+                },
+            });
+
+            segment = vec![last];
+            segment_start = distance;
+        }
+
+        profile
+    }
+}
+
+impl From<&ConfidenceSegment> for ConfidenceSegmentOutput {
+    fn from(value: &ConfidenceSegment) -> Self {
+        Self {
+            start_distance: value.start_distance,
+            end_distance: value.end_distance,
+            start_time: value.start_time,
+            end_time: value.end_time,
+            confidence: value.confidence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    /// Trace heading east at ~15 meters per second, drifting north after `drift_from` points
+    /// to a parallel road ~330 meters away.
+    fn create_trace(id: &str, drift_from: i64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..61, |i| {
+                (
+                    2.3522 + i as f64 * 0.002,
+                    48.8566 + ((i - drift_from).max(0) as f64 * 0.001).min(0.003),
+                    Duration::seconds(i * 10),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_confidence_profile() {
+        let driver = create_trace("driver", 61);
+        // The passenger takes a parallel road for the last third of the route
+        let passenger = create_trace("passenger", 40);
+        let (start, end) = (driver.points[0].timestamp, driver.points[60].timestamp);
+
+        let profile =
+            driver.confidence_profile(&passenger, start, end, &ConfidenceProfileConfig::default());
+
+        assert_eq!(profile.len(), 15);
+        assert!((profile[14].end_distance - 8_780.0).abs() < 10.0);
+        assert!(profile[..10].iter().all(|s| s.confidence == 1.0));
+        assert!(profile[12..].iter().all(|s| s.confidence == 0.0));
+        assert!((confident_share(&profile, 0.9) - 2.0 / 3.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::Duration;
+use geo::{HaversineDistance, Point};
+
+use crate::{config::ResampleStep, point::PointWithId, trace::Trace};
+
+impl<T> Trace<T> {
+    /// Resamples the trace at a fixed step, keeping both edges and any recorded point that falls
+    /// exactly on the step. Every other point is interpolated between its recorded neighbours.
+    pub fn resampled(&self, step: ResampleStep) -> Trace<T> {
+        let points = match step {
+            ResampleStep::TimeMs(step_ms) if step_ms > 0 => resample_by_time(&self.points, step_ms),
+            ResampleStep::Meters(step_m) if step_m > 0.0 => {
+                resample_by_distance(&self.points, step_m)
+            }
+            _ => self.points.clone(),
+        };
+
+        self.with_points(points)
+    }
+}
+
+fn resample_by_time(points: &[PointWithId], step_ms: i64) -> Vec<PointWithId> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return points.to_vec();
+    };
+
+    let step = Duration::milliseconds(step_ms);
+    let mut resampled = vec![first.clone()];
+    let mut target = first.timestamp + step;
+
+    for window in points.windows(2) {
+        let (prev, next) = (&window[0], &window[1]);
+
+        while target < next.timestamp {
+            let ratio = target
+                .signed_duration_since(prev.timestamp)
+                .num_milliseconds() as f64
+                / next.get_ms_delta_with(prev) as f64;
+
+            resampled.push(prev.interpolate(next, ratio));
+            target += step;
+        }
+
+        if target == next.timestamp {
+            resampled.push(next.clone());
+            target += step;
+        }
+    }
+
+    if resampled.last().map(|p| &p.id) != Some(&last.id) {
+        resampled.push(last.clone());
+    }
+
+    resampled
+}
+
+fn resample_by_distance(points: &[PointWithId], step_m: f64) -> Vec<PointWithId> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return points.to_vec();
+    };
+
+    let mut resampled = vec![first.clone()];
+    let mut travelled = 0.0;
+    let mut target = step_m;
+
+    for window in points.windows(2) {
+        let (prev, next) = (&window[0], &window[1]);
+        let length = Point::from(prev).haversine_distance(&Point::from(next));
+
+        while length > 0.0 && travelled + length >= target {
+            let ratio = (target - travelled) / length;
+
+            if ratio >= 1.0 {
+                resampled.push(next.clone());
+            } else {
+                resampled.push(prev.interpolate(next, ratio));
+            }
+
+            target += step_m;
+        }
+
+        travelled += length;
+    }
+
+    if resampled.last().map(|p| &p.id) != Some(&last.id) {
+        resampled.push(last.clone());
+    }
+
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point::PointSource, test_utils::TraceBuilder};
+
+    fn create_trace() -> Trace {
+        let fixes = [(0, 0.0), (10, 0.001), (40, 0.004)];
+
+        TraceBuilder::new("trace_1")
+            .fixes(0..3, |i| {
+                let (seconds, dx) = fixes[i as usize];
+                (2.3522 + dx, 48.8566, Duration::seconds(seconds))
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_resampled_by_time() {
+        let trace = create_trace().resampled(ResampleStep::TimeMs(10_000));
+
+        let ids: Vec<&str> = trace.points.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids[0], "trace_1_0");
+        assert_eq!(ids[1], "trace_1_1");
+        assert_eq!(ids[4], "trace_1_2");
+        assert_eq!(
+            trace.points[2].source,
+            PointSource::Interpolated {
+                previous_id: "trace_1_1".to_string(),
+                next_id: "trace_1_2".to_string(),
+            }
+        );
+        assert_eq!(trace.points[2].get_ms_delta_with(&trace.points[1]), 10_000);
+    }
+
+    #[test]
+    fn test_resampled_by_distance() {
+        let trace = create_trace();
+        let length = trace.haversine_length();
+        let resampled = trace.resampled(ResampleStep::Meters(50.0));
+
+        assert_eq!(resampled.points.len(), (length / 50.0).floor() as usize + 2);
+        assert!((resampled.haversine_length() - length).abs() < 0.01);
+        assert!(resampled.points.last().unwrap().is_recorded());
+    }
+
+    #[test]
+    fn test_resampled_through_stop() {
+        let trace = TraceBuilder::new("trace_1")
+            .fixes(0..3, |i| (2.3522, 48.8566, Duration::seconds(i * 30)))
+            .build()
+            .resampled(ResampleStep::TimeMs(10_000));
+
+        assert_eq!(trace.points.len(), 7);
+        assert!(trace.points.iter().all(|p| p.x == 2.3522 && p.y == 48.8566));
+    }
+}
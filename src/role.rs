@@ -0,0 +1,88 @@
+use geo::{HaversineDistance, Point};
+
+use crate::{config::RoleSwapConfig, output::RoleSwapOutput, trace::Trace};
+
+/// Distances travelled by each user while the other one was not recording.
+#[derive(Debug, Clone, Default)]
+pub struct RoleSwap {
+    pub driver_lead_distance: f64,
+    pub driver_trail_distance: f64,
+    pub passenger_lead_distance: f64,
+    pub passenger_trail_distance: f64,
+    /// The passenger trace follows the driver's pattern: it travelled to the pickup point where
+    /// the driver trace starts and kept going after the driver trace ended.
+    pub suspected: bool,
+}
+
+impl<T> Trace<T> {
+    /// Distances travelled before the start and after the end of the other trace.
+    fn lead_and_trail<U>(&self, other: &Trace<U>) -> (f64, f64) {
+        let (start, end) = self.get_edges();
+        let (other_start, other_end) = other.get_edges();
+
+        (
+            self.distance_between(start.timestamp, other_start.timestamp),
+            self.distance_between(other_end.timestamp, end.timestamp),
+        )
+    }
+}
+
+pub fn role_swap_analysis<T, U>(
+    driver: &Trace<T>,
+    passenger: &Trace<U>,
+    config: &RoleSwapConfig,
+) -> RoleSwap {
+    let (driver_lead_distance, driver_trail_distance) = driver.lead_and_trail(passenger);
+    let (passenger_lead_distance, passenger_trail_distance) = passenger.lead_and_trail(driver);
+
+    let (driver_start, _) = driver.get_edges();
+    let picked_up_at_driver_start = passenger
+        .position_at(driver_start.timestamp)
+        .is_some_and(|p| p.haversine_distance(&Point::from(driver_start)) <= config.radius);
+
+    let suspected = picked_up_at_driver_start
+        && passenger_lead_distance >= config.min_distance
+        && passenger_trail_distance >= config.min_distance
+        && driver_lead_distance < config.min_distance
+        && driver_trail_distance < config.min_distance;
+
+    RoleSwap {
+        driver_lead_distance,
+        driver_trail_distance,
+        passenger_lead_distance,
+        passenger_trail_distance,
+        suspected,
+    }
+}
+
+impl From<&RoleSwap> for RoleSwapOutput {
+    fn from(value: &RoleSwap) -> Self {
+        Self {
+            driver_lead_distance: value.driver_lead_distance,
+            driver_trail_distance: value.driver_trail_distance,
+            passenger_lead_distance: value.passenger_lead_distance,
+            passenger_trail_distance: value.passenger_trail_distance,
+            suspected: value.suspected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::eastward_trace;
+
+    #[test]
+    fn test_role_swap() {
+        let config = RoleSwapConfig::default();
+        let driver = eastward_trace("driver", 0, 20, 0.0);
+        let passenger = eastward_trace("passenger", 5, 15, 0.0);
+
+        let genuine = role_swap_analysis(&driver, &passenger, &config);
+        let swapped = role_swap_analysis(&passenger, &driver, &config);
+
+        assert!(!genuine.suspected);
+        assert!(genuine.driver_lead_distance > 4_000.0);
+        assert!(swapped.suspected);
+    }
+}
@@ -0,0 +1,137 @@
+use geo::{HausdorffDistance, HaversineDistance, LineString, Point};
+
+use crate::{
+    common::Lcss, config::SimilarityConfig, output::SimilarityOutput, spatial::to_local_meters,
+    trace::Trace,
+};
+
+/// Distances between both traces, in meters, complementing the Fréchet-based confidence.
+#[derive(Debug, Clone, Default)]
+pub struct Similarity {
+    pub hausdorff_distance: f64,
+    /// Mean distance between the points paired by dynamic time warping.
+    pub dtw_distance: f64,
+    /// Share of the shortest trace matched by the longest common subsequence.
+    pub lcss_ratio: f64,
+}
+
+/// Cumulated cost and length of the cheapest warping path ending at a cell.
+#[derive(Clone, Copy)]
+struct Warping {
+    cost: f64,
+    steps: usize,
+}
+
+impl Warping {
+    const UNREACHABLE: Warping = Warping {
+        cost: f64::INFINITY,
+        steps: 0,
+    };
+}
+
+fn dtw_distance(a: &[Point<f64>], b: &[Point<f64>]) -> f64 {
+    let mut previous = vec![Warping::UNREACHABLE; b.len() + 1];
+    previous[0] = Warping {
+        cost: 0.0,
+        steps: 0,
+    };
+
+    for point in a {
+        let mut current = vec![Warping::UNREACHABLE; b.len() + 1];
+
+        for (j, other) in b.iter().enumerate() {
+            let best = [previous[j], previous[j + 1], current[j]]
+                .into_iter()
+                .min_by(|x, y| x.cost.total_cmp(&y.cost))
+                .unwrap_or(Warping::UNREACHABLE);
+
+            current[j + 1] = Warping {
+                cost: best.cost + point.haversine_distance(other),
+                steps: best.steps + 1,
+            };
+        }
+
+        previous = current;
+    }
+
+    match previous[b.len()] {
+        Warping { cost, steps } if steps > 0 && cost.is_finite() => cost / steps as f64,
+        _ => 0.0,
+    }
+}
+
+impl Trace {
+    pub fn similarity_with(&self, other: &Trace, config: &SimilarityConfig) -> Similarity {
+        if self.points.is_empty() || other.points.is_empty() {
+            return Similarity::default();
+        }
+
+        let points: Vec<Point<f64>> = self.points.iter().map(Point::from).collect();
+        let other_points: Vec<Point<f64>> = other.points.iter().map(Point::from).collect();
+
+        let origin = points[0];
+        let project = |points: &[Point<f64>]| -> LineString<f64> {
+            points
+                .iter()
+                .map(|p| to_local_meters(origin, p.0))
+                .collect()
+        };
+
+        let lcss = Lcss {
+            radius: config.lcss_radius,
+            max_delta_ms: config.lcss_max_delta_ms,
+        };
+        let shortest = self.points.len().min(other.points.len());
+
+        Similarity {
+            hausdorff_distance: project(&points).hausdorff_distance(&project(&other_points)),
+            dtw_distance: dtw_distance(&points, &other_points),
+            lcss_ratio: lcss.subsequence(self, other).length as f64 / shortest as f64,
+        }
+    }
+}
+
+impl From<&Similarity> for SimilarityOutput {
+    fn from(value: &Similarity) -> Self {
+        Self {
+            hausdorff_distance: value.hausdorff_distance,
+            dtw_distance: value.dtw_distance,
+            lcss_ratio: value.lcss_ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    /// Trace heading east `dy` degrees north of the reference, its last point pulled `spike`
+    /// degrees further north.
+    fn create_trace(id: &str, dy: f64, spike: f64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..20, |i| {
+                (
+                    2.3522 + i as f64 * 0.002,
+                    48.8566 + dy + if i == 19 { spike } else { 0.0 },
+                    Duration::seconds(i * 10),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_similarity() {
+        let config = SimilarityConfig::default();
+        let driver = create_trace("driver", 0.0, 0.0);
+        // ~11 meters north, the last point ~1.1 kilometers north
+        let passenger = create_trace("passenger", 0.0001, 0.01);
+
+        let similarity = driver.similarity_with(&passenger, &config);
+
+        assert!((similarity.hausdorff_distance - 1_124.0).abs() < 1.0);
+        assert!((similarity.dtw_distance - 66.7).abs() < 0.1);
+        assert_eq!(similarity.lcss_ratio, 0.95);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use geo::{Coord, LineString, Point, SimplifyIdx, SimplifyVwIdx, SimplifyVwPreserve};
+
+use crate::{
+    config::{SimplifyAlgorithm, SimplifyConfig},
+    spatial::to_local_meters,
+    trace::{NotSimplified, Simplified, Trace},
+};
+
+/// Epsilon in degrees used when no tolerance in meters is configured.
+pub const SIMPLIFY_EPSILON: f64 = 0.00001;
+
+/// Indices of the coordinates of `simplified` in `linestring`, both being in the same order.
+/// Ambiguous when a coordinate is visited twice, in which case `None` is returned.
+fn retained_indices(
+    linestring: &LineString<f64>,
+    simplified: &LineString<f64>,
+) -> Option<Vec<usize>> {
+    let mut visited = HashSet::new();
+
+    if !linestring
+        .coords()
+        .all(|c| visited.insert((c.x.to_bits(), c.y.to_bits())))
+    {
+        return None;
+    }
+
+    let mut coords = linestring.coords().enumerate();
+
+    Some(
+        simplified
+            .coords()
+            .filter_map(|kept| coords.find(|(_, c)| *c == kept).map(|(idx, _)| idx))
+            .collect(),
+    )
+}
+
+impl Trace<NotSimplified> {
+    pub fn simplified_with(&self, config: &SimplifyConfig) -> Trace<Simplified> {
+        let indices = self.distinct_indices();
+        let coords = indices.iter().map(|idx| Coord::from(&self.points[*idx]));
+
+        let (linestring, epsilon): (LineString<f64>, f64) = match config.tolerance {
+            Some(tolerance) => {
+                let origin = Point::from(&self.points[indices[0]]);
+                let projected = coords.map(|c| to_local_meters(origin, c)).collect();
+
+                (projected, tolerance)
+            }
+            None => (coords.collect(), SIMPLIFY_EPSILON),
+        };
+
+        let kept = match config.algorithm {
+            SimplifyAlgorithm::RamerDouglasPeucker => linestring.simplify_idx(&epsilon),
+            SimplifyAlgorithm::VisvalingamWhyatt => {
+                linestring.simplify_vw_idx(&(epsilon * epsilon))
+            }
+            // Falls back to Visvalingam–Whyatt on a trace going back over one of its fixes
+            SimplifyAlgorithm::TopologyPreserving => retained_indices(
+                &linestring,
+                &linestring.simplify_vw_preserve(&(epsilon * epsilon)),
+            )
+            .unwrap_or_else(|| linestring.simplify_vw_idx(&(epsilon * epsilon))),
+        };
+
+        self.with_indices(kept.into_iter().map(|idx| indices[idx]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    /// Straight line east with a 0.5 meter wobble and a 20 meters bump in the middle.
+    fn create_trace() -> Trace {
+        TraceBuilder::new("trace_1")
+            .fixes(0..21, |i| {
+                let dy = if i == 10 {
+                    0.00018
+                } else {
+                    (i % 2) as f64 * 0.0000045
+                };
+                (
+                    2.3522 + i as f64 * 0.001,
+                    48.8566 + dy,
+                    Duration::seconds(i * 10),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_simplified_with() {
+        let trace = create_trace();
+
+        for algorithm in [
+            SimplifyAlgorithm::RamerDouglasPeucker,
+            SimplifyAlgorithm::VisvalingamWhyatt,
+            SimplifyAlgorithm::TopologyPreserving,
+        ] {
+            let fine = trace.simplified_with(&SimplifyConfig {
+                algorithm,
+                tolerance: Some(0.1),
+            });
+            let coarse = trace.simplified_with(&SimplifyConfig {
+                algorithm,
+                tolerance: Some(10.0),
+            });
+
+            let ids: Vec<&str> = coarse.points.iter().map(|p| p.id.as_str()).collect();
+
+            assert_eq!(fine.points.len(), 21, "{algorithm:?}");
+            assert!(ids.len() < 10, "{algorithm:?}");
+            assert_eq!(ids.first(), Some(&"trace_1_0"), "{algorithm:?}");
+            assert!(ids.contains(&"trace_1_10"), "{algorithm:?}");
+            assert_eq!(ids.last(), Some(&"trace_1_20"), "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn test_topology_preserving_revisit() {
+        // A, B, a 2 meters step to C, back to B and on to E
+        let coords = [
+            (0.0, 0.0),
+            (0.001, 0.001),
+            (0.00103, 0.001),
+            (0.001, 0.001),
+            (0.002, 0.0),
+        ];
+        let trace = TraceBuilder::new("trace_1")
+            .fixes(0..5, |i| {
+                let (dx, dy) = coords[i as usize];
+                (2.3522 + dx, 48.8566 + dy, Duration::seconds(i * 30))
+            })
+            .build();
+
+        let ids = |algorithm| {
+            trace
+                .simplified_with(&SimplifyConfig {
+                    algorithm,
+                    tolerance: Some(5.0),
+                })
+                .points
+                .into_iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>()
+        };
+
+        // The kept visit of B is the second one, with its own id and timestamp
+        assert_eq!(
+            ids(SimplifyAlgorithm::VisvalingamWhyatt),
+            ["trace_1_0", "trace_1_3", "trace_1_4"]
+        );
+        assert_eq!(
+            ids(SimplifyAlgorithm::TopologyPreserving),
+            ["trace_1_0", "trace_1_3", "trace_1_4"]
+        );
+    }
+}
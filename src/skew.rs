@@ -0,0 +1,126 @@
+use chrono::Duration;
+use geo::{HaversineDistance, Point};
+
+use crate::{config::ClockSkewConfig, output::ClockSkewOutput, trace::Trace};
+
+const MIN_SKEW_SAMPLES: usize = 5;
+const MIN_SKEW_OVERLAP_RATIO: f64 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct ClockSkew {
+    /// Milliseconds to add to the passenger timestamps to align them with the driver clock.
+    pub offset_ms: i64,
+    pub mean_distance: f64,
+    pub implausible: bool,
+    pub applied: bool,
+}
+
+impl ClockSkew {
+    /// Finds the offset that minimizes the mean distance between each passenger point and the
+    /// driver position interpolated at the shifted timestamp. Smaller offsets win ties, so two
+    /// stationary traces yield no skew.
+    pub fn estimate<T, U>(
+        driver: &Trace<T>,
+        passenger: &Trace<U>,
+        config: &ClockSkewConfig,
+    ) -> Option<Self> {
+        let step = config.step_ms.max(1);
+        let steps = config.max_search_ms.max(0) / step;
+        let min_samples = MIN_SKEW_SAMPLES
+            .max((passenger.points.len() as f64 * MIN_SKEW_OVERLAP_RATIO).ceil() as usize);
+
+        let offsets = (0..=steps).flat_map(|i| {
+            if i == 0 {
+                vec![0]
+            } else {
+                vec![-i * step, i * step]
+            }
+        });
+
+        let mut best: Option<(i64, f64)> = None;
+
+        for offset_ms in offsets {
+            let offset = Duration::milliseconds(offset_ms);
+
+            let distances: Vec<f64> = passenger
+                .points
+                .iter()
+                .filter_map(|p| {
+                    driver
+                        .position_at(p.timestamp + offset)
+                        .map(|position| position.haversine_distance(&Point::from(p)))
+                })
+                .collect();
+
+            if distances.len() < min_samples {
+                continue;
+            }
+
+            let mean_distance = distances.iter().sum::<f64>() / distances.len() as f64;
+
+            if best.is_none_or(|(_, best_distance)| mean_distance < best_distance) {
+                best = Some((offset_ms, mean_distance));
+            }
+        }
+
+        best.map(|(offset_ms, mean_distance)| Self {
+            offset_ms,
+            mean_distance,
+            implausible: offset_ms.abs() > config.max_plausible_ms,
+            applied: false,
+        })
+    }
+}
+
+impl From<&ClockSkew> for ClockSkewOutput {
+    fn from(value: &ClockSkew) -> Self {
+        Self {
+            offset_ms: value.offset_ms,
+            mean_distance: value.mean_distance,
+            implausible: value.implausible,
+            applied: value.applied,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+
+    fn create_trace(id: &str, offset_s: i64) -> Trace {
+        TraceBuilder::new(id)
+            .fixes(0..20, |i| {
+                (
+                    2.3522 + i as f64 * 0.001,
+                    48.8566,
+                    Duration::seconds(i * 10 + offset_s),
+                )
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_estimate_skew() {
+        let driver = create_trace("driver", 0);
+        let passenger = create_trace("passenger", 60);
+        let config = ClockSkewConfig::default();
+
+        let skew = ClockSkew::estimate(&driver, &passenger, &config).unwrap();
+
+        assert_eq!(skew.offset_ms, -60_000);
+        assert!(skew.mean_distance < 1.0);
+        assert!(!skew.implausible);
+    }
+
+    #[test]
+    fn test_estimate_no_skew() {
+        let driver = create_trace("driver", 0);
+        let passenger = create_trace("passenger", 0);
+        let config = ClockSkewConfig::default();
+
+        let skew = ClockSkew::estimate(&driver, &passenger, &config).unwrap();
+
+        assert_eq!(skew.offset_ms, 0);
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+};
+
+use geo::{
+    Closest, ClosestPoint, Coord, EuclideanDistance, HaversineDistance, Line, LineString, Point,
+};
+use rstar::{primitives::GeomWithData, RTree, AABB};
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// R-tree of line segments, each tagged with the index of the geometry it belongs to.
+pub type SegmentIndex = RTree<GeomWithData<Line<f64>, usize>>;
+
+pub fn segment_index<'a, I>(linestrings: I) -> SegmentIndex
+where
+    I: IntoIterator<Item = (usize, &'a LineString<f64>)>,
+{
+    RTree::bulk_load(
+        linestrings
+            .into_iter()
+            .flat_map(|(idx, linestring)| {
+                linestring
+                    .lines()
+                    .map(move |line| GeomWithData::new(line, idx))
+            })
+            .collect(),
+    )
+}
+
+/// Bounding box of the `radius` meters around `point`.
+pub fn envelope_around(point: Point<f64>, radius: f64) -> AABB<Point<f64>> {
+    let dy = radius / METERS_PER_DEGREE;
+    let dx = dy / point.y().to_radians().cos().max(0.01);
+
+    AABB::from_corners(
+        Point::new(point.x() - dx, point.y() - dy),
+        Point::new(point.x() + dx, point.y() + dy),
+    )
+}
+
+/// Equirectangular projection in meters around `origin`, accurate at the scale of a journey.
+pub fn to_local_meters(origin: Point<f64>, coord: Coord<f64>) -> Coord<f64> {
+    Coord {
+        x: (coord.x - origin.x()) * METERS_PER_DEGREE * origin.y().to_radians().cos(),
+        y: (coord.y - origin.y()) * METERS_PER_DEGREE,
+    }
+}
+
+/// Closest point of `line` to `point`, with its haversine distance in meters.
+pub fn project_on_line(line: &Line<f64>, point: Point<f64>) -> Option<(Point<f64>, f64)> {
+    match line.closest_point(&point) {
+        Closest::SinglePoint(p) | Closest::Intersection(p) => {
+            Some((p, p.haversine_distance(&point)))
+        }
+        Closest::Indeterminate => None,
+    }
+}
+
+/// Segments of the index within `radius` meters of `point`, with their distance.
+pub fn segments_within<'a>(
+    index: &'a SegmentIndex,
+    point: Point<f64>,
+    radius: f64,
+) -> impl Iterator<Item = (&'a GeomWithData<Line<f64>, usize>, Point<f64>, f64)> + 'a {
+    index
+        .locate_in_envelope_intersecting(&envelope_around(point, radius))
+        .filter_map(move |segment| {
+            project_on_line(segment.geom(), point)
+                .filter(|(_, distance)| *distance <= radius)
+                .map(|(projected, distance)| (segment, projected, distance))
+        })
+}
+
+/// Coupling of two points, ordered so the heap pops the smallest bottleneck first.
+struct Coupling {
+    bottleneck: f64,
+    i: usize,
+    j: usize,
+}
+
+impl PartialEq for Coupling {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Coupling {}
+
+impl PartialOrd for Coupling {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Coupling {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .bottleneck
+            .total_cmp(&self.bottleneck)
+            .then((self.i + self.j).cmp(&(other.i + other.j)))
+    }
+}
+
+/// Discrete Fréchet distance, equal to `geo::FrechetDistance`. The couplings are explored from
+/// the smallest bottleneck, so only the pairs of points closer than the result are visited: a
+/// band along the diagonal for two traces of the same journey, instead of every pair.
+pub fn frechet_distance(a: &LineString<f64>, b: &LineString<f64>) -> f64 {
+    let (a, b) = (&a.0, &b.0);
+
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let distance = |i: usize, j: usize| Point::from(a[i]).euclidean_distance(&Point::from(b[j]));
+    let mut visited = HashSet::new();
+    let mut heap = BinaryHeap::from([Coupling {
+        bottleneck: distance(0, 0),
+        i: 0,
+        j: 0,
+    }]);
+
+    while let Some(Coupling { bottleneck, i, j }) = heap.pop() {
+        if (i, j) == (a.len() - 1, b.len() - 1) {
+            return bottleneck;
+        }
+
+        if !visited.insert((i, j)) {
+            continue;
+        }
+
+        for (i, j) in [(i + 1, j), (i, j + 1), (i + 1, j + 1)] {
+            if i < a.len() && j < b.len() && !visited.contains(&(i, j)) {
+                heap.push(Coupling {
+                    bottleneck: bottleneck.max(distance(i, j)),
+                    i,
+                    j,
+                });
+            }
+        }
+    }
+
+    unreachable!("the last coupling is reachable from the first one")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::FrechetDistance;
+
+    #[test]
+    fn test_frechet_distance() {
+        let linestring = |count: usize, seed: usize| -> LineString<f64> {
+            (0..count)
+                .map(|i| {
+                    let wobble = ((i * 7 + seed * 3) % 11) as f64 * 0.0001;
+                    Coord {
+                        x: 2.3522 + i as f64 * 0.001 + wobble,
+                        y: 48.8566 + ((i + seed) % 5) as f64 * 0.0003,
+                    }
+                })
+                .collect()
+        };
+
+        for (a, b) in [
+            (linestring(40, 0), linestring(35, 1)),
+            (linestring(1, 0), linestring(20, 2)),
+            (linestring(30, 3), linestring(30, 3)),
+            (
+                linestring(25, 4),
+                linestring(50, 5).0.into_iter().rev().collect(),
+            ),
+        ] {
+            assert_eq!(frechet_distance(&a, &b), a.frechet_distance(&b));
+        }
+
+        assert_eq!(frechet_distance(&linestring(0, 0), &linestring(5, 0)), 0.0);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use crate::{output::SpoofingOutput, point::PointWithId, trace::Trace};
+
+const MIN_SPOOFING_POINTS: usize = 10;
+/// A single pattern is not enough evidence to score a trace.
+const MIN_KNOWN_PATTERNS: usize = 2;
+/// Coordinates rounded to this many decimals look generated, real fixes carry more.
+const GRID_DECIMALS: i32 = 5;
+
+/// Scores in `[0, 1]` of the patterns left by mock-location apps, higher is more suspicious.
+/// Patterns are `None` when the device did not report the data they rely on.
+#[derive(Debug, Clone, Default)]
+pub struct SpoofingAnalysis {
+    /// Unknown when the fixes are stamped on the GPS second, which makes them regular anyway.
+    pub regular_intervals: Option<f64>,
+    pub constant_accuracy: Option<f64>,
+    pub zero_altitude: Option<f64>,
+    pub identical_speeds: Option<f64>,
+    pub grid_coordinates: Option<f64>,
+}
+
+impl SpoofingAnalysis {
+    /// Mean of the known patterns, `None` when too few of them are known.
+    pub fn score(&self) -> Option<f64> {
+        let known: Vec<f64> = [
+            self.regular_intervals,
+            self.constant_accuracy,
+            self.zero_altitude,
+            self.identical_speeds,
+            self.grid_coordinates,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        (known.len() >= MIN_KNOWN_PATTERNS)
+            .then(|| known.iter().fold(0.0, |acc, s| acc + s) / known.len() as f64)
+    }
+
+    /// Whether the score reaches `threshold`, traces without a score never do.
+    pub fn reaches(&self, threshold: Option<f64>) -> bool {
+        self.score()
+            .zip(threshold)
+            .is_some_and(|(score, threshold)| score >= threshold)
+    }
+}
+
+/// Share of the values equal to the most frequent one, `None` without values.
+fn mode_ratio<I: Iterator<Item = u64>>(values: I) -> Option<f64> {
+    let mut total = 0;
+    let counts = values.fold(HashMap::new(), |mut acc, value| {
+        total += 1;
+        *acc.entry(value).or_insert(0) += 1;
+        acc
+    });
+
+    counts.values().max().map(|max| *max as f64 / total as f64)
+}
+
+/// Share of the values matching `predicate`, `None` without values.
+fn share<T, I: Iterator<Item = T>, F: Fn(T) -> bool>(values: I, predicate: F) -> Option<f64> {
+    let (total, matching) = values.fold((0, 0), |(total, matching), value| {
+        (total + 1, matching + usize::from(predicate(value)))
+    });
+
+    (total > 0).then(|| matching as f64 / total as f64)
+}
+
+fn is_on_grid(value: f64) -> bool {
+    let scaled = value * 10f64.powi(GRID_DECIMALS);
+
+    (scaled - scaled.round()).abs() < 1e-6
+}
+
+impl<T> Trace<T> {
+    pub fn spoofing_analysis(&self) -> SpoofingAnalysis {
+        let points: Vec<PointWithId> = self
+            .points
+            .iter()
+            .filter(|p| p.is_recorded())
+            .cloned()
+            .collect();
+
+        if points.len() < MIN_SPOOFING_POINTS {
+            return SpoofingAnalysis::default();
+        }
+
+        let on_gps_second = points
+            .iter()
+            .all(|p| p.timestamp.timestamp_subsec_millis() == 0);
+        let regular_intervals = if on_gps_second {
+            None
+        } else {
+            mode_ratio(
+                points
+                    .windows(2)
+                    .map(|w| w[1].get_ms_delta_with(&w[0]) as u64),
+            )
+        };
+
+        let constant_accuracy =
+            mode_ratio(points.iter().filter_map(|p| p.accuracy).map(f64::to_bits));
+
+        let zero_altitude = share(points.iter().filter_map(|p| p.altitude), |a| a == 0.0);
+
+        let identical_speeds = mode_ratio(
+            points
+                .iter()
+                .filter_map(|p| p.speed)
+                .filter(|s| *s > 0.0)
+                .map(f64::to_bits),
+        );
+
+        let grid_coordinates = share(points.iter(), |p| is_on_grid(p.x) && is_on_grid(p.y));
+
+        SpoofingAnalysis {
+            regular_intervals,
+            constant_accuracy,
+            zero_altitude,
+            identical_speeds,
+            grid_coordinates,
+        }
+    }
+}
+
+impl From<&SpoofingAnalysis> for SpoofingOutput {
+    fn from(value: &SpoofingAnalysis) -> Self {
+        Self {
+            score: value.score(),
+            regular_intervals: value.regular_intervals,
+            constant_accuracy: value.constant_accuracy,
+            zero_altitude: value.zero_altitude,
+            identical_speeds: value.identical_speeds,
+            grid_coordinates: value.grid_coordinates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SpoofingConfig, test_utils::TraceBuilder};
+    use chrono::Duration;
+
+    fn create_trace(mocked: bool) -> Trace {
+        let noise = |i: i64| {
+            if mocked {
+                0.0
+            } else {
+                0.000001234 * (i % 3) as f64
+            }
+        };
+
+        TraceBuilder::new("trace_1")
+            .fixes(0..20, |i| {
+                // Mock-location apps stamp the fixes with the system clock
+                let jitter = if mocked { 137 } else { (i * 37) % 11 };
+                (
+                    2.35 + i as f64 * 0.001 + noise(i),
+                    48.85 + noise(i),
+                    Duration::milliseconds(i * 1000 + jitter),
+                )
+            })
+            .with(|i, p| {
+                p.speed = Some(if mocked { 10.0 } else { 9.0 + (i % 4) as f64 });
+                p.accuracy = Some(if mocked { 3.0 } else { 4.0 + (i % 5) as f64 });
+                p.altitude = Some(if mocked { 0.0 } else { 35.0 });
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_spoofing_analysis_mocked() {
+        let analysis = create_trace(true).spoofing_analysis();
+
+        assert_eq!(analysis.regular_intervals, Some(1.0));
+        assert_eq!(analysis.constant_accuracy, Some(1.0));
+        assert_eq!(analysis.zero_altitude, Some(1.0));
+        assert_eq!(analysis.identical_speeds, Some(1.0));
+        assert_eq!(analysis.score(), Some(1.0));
+    }
+
+    #[test]
+    fn test_spoofing_analysis_genuine() {
+        let analysis = create_trace(false).spoofing_analysis();
+
+        assert_eq!(analysis.zero_altitude, Some(0.0));
+        assert!(analysis.score().unwrap() < 0.5);
+    }
+
+    #[test]
+    fn test_spoofing_analysis_without_metadata() {
+        let config = SpoofingConfig::default();
+        // 1 Hz device stamping its fixes on the GPS second, without accuracy, altitude or speed
+        let trace = |decimals: i32| {
+            let scale = 10f64.powi(decimals);
+            TraceBuilder::new("trace_1")
+                .fixes(0..60, |i| {
+                    (
+                        ((2.35 + i as f64 * 0.000123) * scale).round() / scale,
+                        ((48.85 + i as f64 * 0.000071) * scale).round() / scale,
+                        Duration::seconds(i),
+                    )
+                })
+                .build()
+                .spoofing_analysis()
+        };
+
+        for analysis in [trace(7), trace(5)] {
+            assert_eq!(analysis.regular_intervals, None);
+            assert_eq!(analysis.constant_accuracy, None);
+            assert_eq!(analysis.zero_altitude, None);
+            assert_eq!(analysis.score(), None);
+            assert!(!analysis.reaches(config.review_score));
+            assert!(!analysis.reaches(config.reject_score));
+        }
+    }
+}
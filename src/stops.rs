@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use geo::{HaversineDistance, Point};
+
+use crate::{
+    config::StopsConfig,
+    output::{StopOutput, TraceStopsOutput},
+    trace::Trace,
+};
+
+#[derive(Debug, Clone)]
+pub struct Stop {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub location: Point<f64>,
+    pub points_count: usize,
+}
+
+impl Stop {
+    pub fn duration_ms(&self) -> i64 {
+        self.end_time
+            .signed_duration_since(self.start_time)
+            .num_milliseconds()
+    }
+}
+
+impl<T> Trace<T> {
+    /// Groups consecutive points staying within `radius` meters of the first one for at least
+    /// `min_duration_ms`.
+    pub fn stops(&self, config: &StopsConfig) -> Vec<Stop> {
+        let mut stops = vec![];
+        let mut start = 0;
+
+        while start < self.points.len() {
+            let anchor = Point::from(&self.points[start]);
+            let end = self.points[start..]
+                .iter()
+                .position(|p| anchor.haversine_distance(&Point::from(p)) > config.radius)
+                .map_or(self.points.len(), |offset| start + offset);
+
+            let cluster = &self.points[start..end];
+            let (first, last) = (&cluster[0], &cluster[cluster.len() - 1]);
+
+            if cluster.len() < 2 || last.get_ms_delta_with(first) < config.min_duration_ms {
+                start += 1;
+                continue;
+            }
+
+            let (x, y) = cluster
+                .iter()
+                .fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+
+            stops.push(Stop {
+                start_time: first.timestamp,
+                end_time: last.timestamp,
+                location: Point::new(x / cluster.len() as f64, y / cluster.len() as f64),
+                points_count: cluster.len(),
+            });
+
+            start = end;
+        }
+
+        stops
+    }
+
+    /// Share of the trace duration spent in stops.
+    pub fn stationary_ratio(&self, stops: &[Stop]) -> f64 {
+        let (start, end) = self.get_edges();
+        let duration = end.get_ms_delta_with(start);
+
+        if duration == 0 {
+            return 1.0;
+        }
+
+        stops.iter().map(Stop::duration_ms).sum::<i64>() as f64 / duration as f64
+    }
+
+    pub fn stops_output(&self, config: &StopsConfig) -> TraceStopsOutput {
+        let stops = self.stops(config);
+
+        TraceStopsOutput {
+            stationary_ratio: self.stationary_ratio(&stops),
+            stops: stops.iter().map(StopOutput::from).collect(),
+        }
+    }
+}
+
+impl From<&Stop> for StopOutput {
+    fn from(value: &Stop) -> Self {
+        Self {
+            start_time: value.start_time,
+            end_time: value.end_time,
+            duration_ms: value.duration_ms(),
+            latitude: value.location.y(),
+            longitude: value.location.x(),
+            points_count: value.points_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TraceBuilder;
+    use chrono::Duration;
+
+    #[test]
+    fn test_stops() {
+        let coords = [
+            (0.0, 0),
+            (0.0001, 60),
+            (0.0002, 120),
+            (0.0001, 180),
+            (0.01, 240),
+            (0.02, 300),
+            (0.03, 360),
+        ];
+        let trace = TraceBuilder::new("trace_1")
+            .fixes(0..coords.len() as i64, |i| {
+                let (dx, seconds) = coords[i as usize];
+                (2.3522 + dx, 48.8566, Duration::seconds(seconds))
+            })
+            .build();
+
+        let stops = trace.stops(&StopsConfig::default());
+
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].points_count, 4);
+        assert_eq!(stops[0].duration_ms(), 180_000);
+        assert_eq!(trace.stationary_ratio(&stops), 0.5);
+    }
+}
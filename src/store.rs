@@ -0,0 +1,57 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    marker::PhantomData,
+    path::PathBuf,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Result;
+
+/// Append-only JSON lines file shared by every validation run.
+pub struct JsonLinesStore<T> {
+    path: PathBuf,
+    entry: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> JsonLinesStore<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entry: PhantomData,
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<T>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut entries = vec![];
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub fn append(&self, entries: &[T]) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+
+        Ok(())
+    }
+}
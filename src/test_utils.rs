@@ -0,0 +1,80 @@
+//! Fixtures shared by the unit tests.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crate::{point::PointWithId, trace::Trace};
+
+/// Time of the first fix of the test traces.
+pub fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap()
+}
+
+/// Recorded fix taken `elapsed` after `start()`.
+pub fn point(id: &str, trace_id: &str, x: f64, y: f64, elapsed: Duration) -> PointWithId {
+    PointWithId {
+        id: id.to_string(),
+        x,
+        y,
+        trace_id: trace_id.to_string(),
+        timestamp: start() + elapsed,
+        ..Default::default()
+    }
+}
+
+/// Builds a trace from generated fixes, the point of index `i` is named `{trace id}_{i}`.
+pub struct TraceBuilder {
+    id: String,
+    points: Vec<PointWithId>,
+}
+
+impl TraceBuilder {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            points: vec![],
+        }
+    }
+
+    /// Appends a fix per index, `fix` giving its coordinates and its time since `start()`.
+    pub fn fixes<I, F>(mut self, indices: I, fix: F) -> Self
+    where
+        I: IntoIterator<Item = i64>,
+        F: Fn(i64) -> (f64, f64, Duration),
+    {
+        self.points.extend(indices.into_iter().map(|i| {
+            let (x, y, elapsed) = fix(i);
+            point(&format!("{}_{i}", self.id), &self.id, x, y, elapsed)
+        }));
+        self
+    }
+
+    /// Edits every point, to set the fields reported by the device.
+    pub fn with<F>(mut self, mut edit: F) -> Self
+    where
+        F: FnMut(usize, &mut PointWithId),
+    {
+        self.points
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, p)| edit(i, p));
+        self
+    }
+
+    pub fn build(self) -> Trace {
+        Trace::new(self.id, self.points)
+    }
+}
+
+/// Trace heading east at ~15 meters per second between `from` and `to` minutes, `dy` degrees
+/// north of the reference.
+pub fn eastward_trace(id: &str, from: i64, to: i64, dy: f64) -> Trace {
+    TraceBuilder::new(id)
+        .fixes(from * 6..=to * 6, |i| {
+            (
+                2.3522 + i as f64 * 0.002,
+                48.8566 + dy,
+                Duration::seconds(i * 10),
+            )
+        })
+        .build()
+}
@@ -1,23 +1,18 @@
-use std::{collections::HashMap, f64, marker::PhantomData};
+use std::{f64, marker::PhantomData};
 
 use chrono::{DateTime, Utc};
-use geo::{
-    EuclideanDistance, FrechetDistance, Geometry, HaversineBearing, HaversineDistance,
-    HaversineLength, LineString, Point, RemoveRepeatedPoints, Simplify,
-};
+use geo::{HaversineDistance, HaversineIntermediate, HaversineLength, LineString, Point};
 
 use crate::{
-    error::JourneyValidationError,
+    common::{CommonSegmentStrategy, Heuristic},
     input::TraceInput,
     output::{PointOutput, TraceOutput},
     point::PointWithId,
+    spatial::frechet_distance,
     // visualize::{visualize, FeatureProperties},
     Result,
 };
 
-const MAX_POINTS_DELTA_IN_METERS: f64 = 1000.0;
-const MAX_BEARING: f64 = 50.0;
-
 pub struct Simplified;
 pub struct NotSimplified;
 
@@ -25,245 +20,25 @@ pub struct NotSimplified;
 pub struct Trace<T = NotSimplified> {
     pub id: String,
     pub points: Vec<PointWithId>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
     status: PhantomData<T>,
 }
 
 impl Trace {
-    pub fn from_linestring<T>(&self, linestring: LineString<f64>) -> Trace<T> {
-        let coords: HashMap<(u64, u64), (String, DateTime<Utc>)> = self
-            .points
-            .iter()
-            .map(|p| {
-                (
-                    (p.x.to_bits(), p.y.to_bits()),
-                    (p.id.to_string(), p.timestamp),
-                )
-            })
-            .collect();
-
-        let points = linestring
-            .into_iter()
-            .filter_map(|coord| {
-                coords
-                    .get(&(coord.x.to_bits(), coord.y.to_bits()))
-                    .map(|(id, timestamp)| PointWithId {
-                        trace_id: self.id.clone(),
-                        id: id.clone(),
-                        timestamp: timestamp.to_owned(),
-                        x: coord.x,
-                        y: coord.y,
-                    })
-            })
-            .collect();
-
-        Trace {
-            id: self.id.clone(),
+    pub fn new(id: String, points: Vec<PointWithId>) -> Self {
+        Self {
+            id,
             points,
+            created_at: None,
+            updated_at: None,
             status: PhantomData,
         }
     }
 
+    /// Common segment of both traces with the historical heuristic, see `common::Heuristic`.
     pub fn common_trace_with(&self, other: &Trace) -> Result<CommonTrace> {
-        let mut all_points: Vec<&PointWithId> =
-            self.points.iter().chain(other.points.iter()).collect();
-        all_points.sort_by_key(|p| p.timestamp);
-
-        let t0 = all_points.first().unwrap();
-        let tx = all_points.last().unwrap();
-        let trace_with_tx = if tx.trace_id == self.id { self } else { other };
-        let ls_with_tx = Geometry::LineString(LineString::from(trace_with_tx));
-
-        let ty_data = all_points.iter().enumerate().rfind(|(_, p)| {
-            if p.trace_id == tx.trace_id {
-                return false;
-            }
-
-            Geometry::Point(Point::from(**p)).euclidean_distance(&ls_with_tx)
-                < MAX_POINTS_DELTA_IN_METERS
-        });
-
-        let (ty_idx, ty) = match ty_data {
-            Some(data) => data,
-            None => return Err(JourneyValidationError::NoCommonPoints),
-        };
-
-        let all_points = &all_points[0..=ty_idx];
-
-        let mut common_points: Vec<&PointWithId> = Vec::with_capacity(all_points.len());
-        let mut idx = 0;
-
-        while idx < all_points.len() {
-            let prev = common_points.last();
-            let mid = all_points[idx];
-            let next = all_points.get(idx + 1);
-
-            idx += 1;
-
-            if idx == 1 {
-                common_points.push(mid);
-                continue;
-            }
-
-            if mid.id == ty.id {
-                common_points.push(mid);
-                break;
-            }
-
-            if prev.is_none() || next.is_none() {
-                common_points.push(mid);
-                continue;
-            }
-
-            let prev = prev.unwrap();
-            let next = next.unwrap();
-
-            if prev.trace_id == mid.trace_id && next.trace_id == mid.trace_id {
-                common_points.push(mid);
-                continue;
-            }
-
-            let prev_point = Point::from(*prev);
-            let next_point = Point::from(*next);
-
-            let prev_next_dist = prev_point.haversine_distance(&next_point);
-
-            if prev.trace_id != mid.trace_id
-                && prev.trace_id == next.trace_id
-                && prev_next_dist < 250.0
-            {
-                common_points.push(next);
-                idx += 1;
-                continue;
-            }
-
-            let mid_point = Point::from(mid);
-            let bearing_prev = mid_point.haversine_bearing(prev_point);
-            let bearing_next = mid_point.haversine_bearing(next_point);
-            let delta = (bearing_next - bearing_prev + 360.0) % 360.0;
-            let angle = if delta <= 180.0 { delta } else { 360.0 - delta };
-
-            if angle >= MAX_BEARING {
-                common_points.push(mid);
-                continue;
-            }
-        }
-
-        common_points.sort_by_key(|p| p.timestamp);
-        let mut filtered_points: Vec<&PointWithId> = Vec::with_capacity(common_points.len());
-
-        let window_size = 5;
-        let max = window_size - 1;
-
-        if common_points.len() > window_size {
-            for window in common_points.windows(window_size) {
-                if window[0].id == t0.id {
-                    filtered_points.push(window[0]);
-                    continue;
-                }
-
-                if window[max].id == ty.id {
-                    filtered_points.push(window[max]);
-                }
-
-                if filtered_points.iter().any(|p| p.id == window[0].id) {
-                    continue;
-                }
-
-                let start_point = Point::from(window[0]);
-                let end_point = Point::from(window[max]);
-                let size = start_point.haversine_distance(&end_point);
-
-                if size > 100.0 {
-                    filtered_points.push(window[0]);
-                    continue;
-                }
-
-                let trace_ids_count = window
-                    .iter()
-                    .map(|p| p.trace_id.clone())
-                    .collect::<Vec<String>>()
-                    .iter()
-                    .fold(HashMap::new(), |mut acc, id| {
-                        *acc.entry(id.clone()).or_insert(0) += 1;
-                        acc
-                    });
-
-                if trace_ids_count.values().any(|&v| v >= max - 1) {
-                    for &point in window {
-                        if trace_ids_count[&point.trace_id] >= max - 1 {
-                            filtered_points.push(point);
-                        }
-                    }
-                }
-
-                filtered_points.push(window[0]);
-            }
-
-            filtered_points.sort_by_key(|p| p.timestamp);
-            common_points = filtered_points;
-            filtered_points = Vec::with_capacity(common_points.len());
-
-            let window_size = 2;
-            let max = window_size - 1;
-
-            for window in common_points.windows(window_size) {
-                if filtered_points.iter().any(|p| p.id == window[0].id) {
-                    continue;
-                }
-
-                if window[0].id == t0.id {
-                    filtered_points.push(window[0]);
-                }
-
-                if window[max].id == ty.id {
-                    filtered_points.push(window[max]);
-                }
-
-                let prev = filtered_points.last();
-
-                if prev.is_none() {
-                    continue;
-                }
-
-                let prev = prev.unwrap();
-
-                let prev_point = Point::from(*prev);
-                let mid_point = Point::from(window[0]);
-                let next_point = Point::from(window[max]);
-                let bearing_prev = mid_point.haversine_bearing(prev_point);
-                let bearing_next = mid_point.haversine_bearing(next_point);
-                let delta = (bearing_next - bearing_prev + 360.0) % 360.0;
-                let angle = if delta <= 180.0 { delta } else { 360.0 - delta };
-
-                if angle == 0.0 || angle >= MAX_BEARING {
-                    filtered_points.push(window[0]);
-                }
-            }
-        } else {
-            filtered_points.clone_from(&common_points);
-        }
-
-        filtered_points.sort_by_key(|p| p.timestamp);
-        let common_linestring = LineString::from(filtered_points).simplify(&0.00001);
-        let common_distance = common_linestring.haversine_length();
-
-        // visualize([
-        //     (common_linestring, FeatureProperties::new().color("#00ffff")),
-        //     (
-        //         LineString::from(self),
-        //         FeatureProperties::new().color("#ff0000"),
-        //     ),
-        //     (
-        //         LineString::from(other),
-        //         FeatureProperties::new().color("#00ff00"),
-        //     ),
-        // ]);
-
-        Ok(CommonTrace {
-            common_distance,
-            common_start_point: PointOutput::from(*t0),
-            common_end_point: PointOutput::from(*ty),
-        })
+        Heuristic.common_segment(self, other)
     }
 }
 
@@ -274,11 +49,29 @@ pub struct CommonTrace {
 }
 
 impl Trace<NotSimplified> {
-    pub fn simplified(&self, epsilon: f64) -> Trace<Simplified> {
-        self.from_linestring(
-            LineString::from(self)
-                .remove_repeated_points()
-                .simplify(&epsilon),
+    /// Indices of the points not sharing the coordinates of the previous one.
+    pub(crate) fn distinct_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = Vec::with_capacity(self.points.len());
+
+        for (idx, point) in self.points.iter().enumerate() {
+            match indices.last() {
+                Some(prev)
+                    if self.points[*prev].x == point.x && self.points[*prev].y == point.y => {}
+                _ => indices.push(idx),
+            }
+        }
+
+        indices
+    }
+
+    /// Keeps the retained points themselves rather than their coordinates, so a point repeated
+    /// while stopped keeps its own id and timestamp.
+    pub(crate) fn with_indices<U, I: IntoIterator<Item = usize>>(&self, indices: I) -> Trace<U> {
+        self.with_points(
+            indices
+                .into_iter()
+                .map(|idx| self.points[idx].clone())
+                .collect(),
         )
     }
 }
@@ -304,7 +97,7 @@ impl Trace<Simplified> {
             .collect::<Vec<Point<f64>>>()
             .into();
 
-        1.0 - ((curr.frechet_distance(&other) * 1000.0) / 100.0).clamp(0.0, 1.0)
+        1.0 - ((frechet_distance(&curr, &other) * 1000.0) / 100.0).clamp(0.0, 1.0)
     }
 }
 
@@ -313,12 +106,74 @@ impl<T> Trace<T> {
         LineString::from(self).haversine_length()
     }
 
+    /// Distance travelled between both timestamps.
+    pub fn distance_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+        let points: Vec<Point> = self
+            .points
+            .iter()
+            .filter(|p| p.timestamp >= from && p.timestamp <= to)
+            .map(Point::from)
+            .collect();
+
+        points
+            .windows(2)
+            .fold(0.0, |acc, w| acc + w[0].haversine_distance(&w[1]))
+    }
+
     pub fn get_edges(&self) -> (&PointWithId, &PointWithId) {
         let start_point = self.points.first().unwrap();
         let end_point = self.points.last().unwrap();
 
         (start_point, end_point)
     }
+
+    /// Interpolated position along the great circle between the two points surrounding `timestamp`.
+    pub fn position_at(&self, timestamp: DateTime<Utc>) -> Option<Point<f64>> {
+        let idx = self.points.partition_point(|p| p.timestamp < timestamp);
+        let next = self.points.get(idx)?;
+
+        if next.timestamp == timestamp {
+            return Some(Point::from(next));
+        }
+
+        let prev = self.points.get(idx.checked_sub(1)?)?;
+        let span = next.get_ms_delta_with(prev);
+
+        if span == 0 {
+            return Some(Point::from(prev));
+        }
+
+        let ratio = timestamp
+            .signed_duration_since(prev.timestamp)
+            .num_milliseconds() as f64
+            / span as f64;
+
+        Some(Point::from(prev).haversine_intermediate(&Point::from(next), ratio))
+    }
+
+    pub fn shifted(&self, offset_ms: i64) -> Trace<T> {
+        let offset = chrono::Duration::milliseconds(offset_ms);
+
+        self.with_points(
+            self.points
+                .iter()
+                .map(|p| PointWithId {
+                    timestamp: p.timestamp + offset,
+                    ..p.clone()
+                })
+                .collect(),
+        )
+    }
+
+    pub(crate) fn with_points<U>(&self, points: Vec<PointWithId>) -> Trace<U> {
+        Trace {
+            id: self.id.clone(),
+            points,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            status: PhantomData,
+        }
+    }
 }
 
 impl From<Trace<Simplified>> for TraceOutput {
@@ -350,6 +205,36 @@ impl From<&TraceInput> for Trace {
         Self {
             id: value.id.clone(),
             points,
+            created_at: Some(value.created_at),
+            updated_at: Some(value.updated_at),
+            status: PhantomData,
+        }
+    }
+}
+
+impl Trace {
+    /// Concatenates the fragments recorded by a single user, after an app crash for instance, into
+    /// one trace named after the earliest fragment. Points are kept as submitted, `normalized`
+    /// sorts and deduplicates them.
+    pub fn from_fragments(fragments: &[&TraceInput]) -> Self {
+        let mut fragments = fragments.to_vec();
+        fragments.sort_by_key(|f| f.created_at);
+
+        let id = fragments[0].id.clone();
+        let points = fragments
+            .iter()
+            .flat_map(|f| f.points.iter())
+            .map(|p| PointWithId {
+                trace_id: id.clone(),
+                ..PointWithId::from(p)
+            })
+            .collect();
+
+        Self {
+            id,
+            points,
+            created_at: fragments.iter().map(|f| f.created_at).min(),
+            updated_at: fragments.iter().map(|f| f.updated_at).max(),
             status: PhantomData,
         }
     }
@@ -358,7 +243,11 @@ impl From<&TraceInput> for Trace {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
+    use crate::{
+        config::SimplifyConfig,
+        test_utils::{start, TraceBuilder},
+    };
+    use chrono::{Duration, TimeZone};
 
     fn create_sample_points() -> (Vec<PointWithId>, Vec<PointWithId>) {
         (
@@ -369,6 +258,7 @@ mod tests {
                     y: 48.8566,
                     trace_id: "trace_1".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap(),
+                    ..Default::default()
                 },
                 PointWithId {
                     id: "2".to_string(),
@@ -376,6 +266,7 @@ mod tests {
                     y: 48.8606,
                     trace_id: "trace_1".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 20, 2).unwrap(),
+                    ..Default::default()
                 },
                 PointWithId {
                     id: "3".to_string(),
@@ -383,6 +274,7 @@ mod tests {
                     y: 48.8738,
                     trace_id: "trace_1".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 20, 4).unwrap(),
+                    ..Default::default()
                 },
             ],
             vec![
@@ -392,6 +284,7 @@ mod tests {
                     y: 48.8566,
                     trace_id: "trace_2".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 0, 0).unwrap(),
+                    ..Default::default()
                 },
                 PointWithId {
                     id: "5".to_string(),
@@ -399,6 +292,7 @@ mod tests {
                     y: 48.8606,
                     trace_id: "trace_2".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 20, 2).unwrap(),
+                    ..Default::default()
                 },
                 PointWithId {
                     id: "6".to_string(),
@@ -406,6 +300,7 @@ mod tests {
                     y: 48.875,
                     trace_id: "trace_2".to_string(),
                     timestamp: Utc.with_ymd_and_hms(2024, 6, 18, 12, 20, 4).unwrap(),
+                    ..Default::default()
                 },
             ],
         )
@@ -417,6 +312,8 @@ mod tests {
         let trace = Trace {
             id: "trace_1".to_string(),
             points,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<NotSimplified>,
         };
 
@@ -431,6 +328,8 @@ mod tests {
         let trace = Trace {
             id: "trace_1".to_string(),
             points,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<NotSimplified>,
         };
 
@@ -444,12 +343,16 @@ mod tests {
         let trace1 = Trace {
             id: "trace_1".to_string(),
             points: points1,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<Simplified>,
         };
 
         let trace2 = Trace {
             id: "trace_2".to_string(),
             points: points2,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<Simplified>,
         };
 
@@ -463,12 +366,16 @@ mod tests {
         let trace1 = Trace {
             id: "trace_1".to_string(),
             points: points1,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<NotSimplified>,
         };
 
         let trace2 = Trace {
             id: "trace_2".to_string(),
             points: points2,
+            created_at: None,
+            updated_at: None,
             status: PhantomData::<NotSimplified>,
         };
 
@@ -478,4 +385,83 @@ mod tests {
         assert_eq!(common_trace.common_start_point.id, "1");
         assert_eq!(common_trace.common_end_point.id, "3");
     }
+
+    #[test]
+    fn test_from_fragments() {
+        let fragment = |id: &str, created_at: &str, points: &[(&str, &str)]| -> TraceInput {
+            serde_json::from_value(serde_json::json!({
+                "id": id,
+                "createdAt": created_at,
+                "updatedAt": created_at,
+                "userId": "driver",
+                "points": points.iter().map(|(point_id, timestamp)| serde_json::json!({
+                    "id": point_id,
+                    "createdAt": timestamp,
+                    "updatedAt": timestamp,
+                    "latitude": 48.8566,
+                    "longitude": 2.3522,
+                    "timestamp": timestamp,
+                    "gpsTraceId": id,
+                })).collect::<Vec<_>>(),
+            }))
+            .unwrap()
+        };
+
+        let second = fragment(
+            "trace_2",
+            "2024-06-18T12:10:00Z",
+            &[("3", "2024-06-18T12:10:00Z"), ("2", "2024-06-18T12:05:00Z")],
+        );
+        let first = fragment(
+            "trace_1",
+            "2024-06-18T12:00:00Z",
+            &[("1", "2024-06-18T12:00:00Z"), ("2", "2024-06-18T12:05:00Z")],
+        );
+
+        let trace = Trace::from_fragments(&[&second, &first]);
+
+        assert_eq!(trace.id, "trace_1");
+        assert_eq!(
+            trace
+                .points
+                .iter()
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1", "2", "3", "2"]
+        );
+        assert!(trace.points.iter().all(|p| p.trace_id == "trace_1"));
+    }
+
+    #[test]
+    fn test_simplified_keeps_identity() {
+        // Stopped at a light on the second point, then turning north
+        let coords = [
+            (2.3522, 48.8566),
+            (2.3532, 48.8566),
+            (2.3532, 48.8566),
+            (2.3542, 48.8566),
+            (2.3542, 48.8576),
+        ];
+        let trace = TraceBuilder::new("trace_1")
+            .fixes(0..5, |i| {
+                let (x, y) = coords[i as usize];
+                (x, y, Duration::seconds(i * 30))
+            })
+            .build();
+
+        let simplified = trace.simplified_with(&SimplifyConfig::default());
+
+        assert_eq!(
+            simplified
+                .points
+                .iter()
+                .map(|p| (p.id.as_str(), p.timestamp))
+                .collect::<Vec<_>>(),
+            [
+                ("trace_1_0", start()),
+                ("trace_1_3", start() + Duration::seconds(90)),
+                ("trace_1_4", start() + Duration::seconds(120)),
+            ]
+        );
+    }
 }